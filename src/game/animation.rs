@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+
+use crate::game::{Brain, Enemy, Flyer, GameDirection, Jumper, Player, Shooter};
+use crate::AppState;
+
+// Every sheet has a row of frames for each clip, in the order of `Clip`
+const SHEET_COLUMNS: usize = 4;
+const SHEET_ROWS: usize = 7;
+const RUN_SPEED: f32 = 0.5;
+// slower falls happen on moving platforms and slopes, they don't count
+const FALL_SPEED: f32 = 4.0;
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(choose_clip)
+                .with_system(animate_sprites),
+        );
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SpriteSheet {
+    pub texture: String,
+    pub frame_size: (f32, f32),
+}
+
+impl SpriteSheet {
+    pub fn atlas(&self, texture: Handle<Image>) -> TextureAtlas {
+        let (width, height) = self.frame_size;
+        TextureAtlas::from_grid(texture, Vec2::new(width, height), SHEET_COLUMNS, SHEET_ROWS)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Clip {
+    Idle,
+    Run,
+    Jump,
+    Fall,
+    Shoot,
+    Hurt,
+    Die,
+}
+
+impl Clip {
+    fn fps(&self) -> f32 {
+        match self {
+            Clip::Idle => 4.,
+            Clip::Run => 10.,
+            Clip::Jump | Clip::Fall => 8.,
+            Clip::Shoot => 16.,
+            Clip::Hurt => 10.,
+            Clip::Die => 8.,
+        }
+    }
+
+    fn looping(&self) -> bool {
+        matches!(self, Clip::Idle | Clip::Run | Clip::Jump | Clip::Fall)
+    }
+}
+
+// Movement clips are picked from the velocity, actions like shooting are played to the end
+// before going back to them
+#[derive(Component)]
+pub struct Animator {
+    clip: Clip,
+    elapsed: f32,
+    action: bool,
+}
+
+impl Default for Animator {
+    fn default() -> Self {
+        Animator {
+            clip: Clip::Idle,
+            elapsed: 0.,
+            action: false,
+        }
+    }
+}
+
+impl Animator {
+    pub fn play(&mut self, clip: Clip) {
+        self.clip = clip;
+        self.elapsed = 0.;
+        self.action = true;
+    }
+
+    fn switch_to(&mut self, clip: Clip) {
+        if self.clip != clip {
+            self.clip = clip;
+            self.elapsed = 0.;
+        }
+    }
+
+    fn frames_played(&self) -> usize {
+        (self.elapsed * self.clip.fps()) as usize
+    }
+
+    pub fn finished(&self) -> bool {
+        !self.clip.looping() && self.frames_played() >= SHEET_COLUMNS
+    }
+
+    fn frame(&self) -> usize {
+        let frame = match self.clip.looping() {
+            true => self.frames_played() % SHEET_COLUMNS,
+            false => self.frames_played().min(SHEET_COLUMNS - 1),
+        };
+        self.clip as usize * SHEET_COLUMNS + frame
+    }
+}
+
+// Dead enemies stay around until their die clip ends, and so does the player on their last
+// life before the level is failed
+#[derive(Component)]
+pub struct Dying;
+
+pub fn start_dying(commands: &mut Commands, entity: Entity, animator: &mut Animator) {
+    animator.play(Clip::Die);
+    commands
+        .entity(entity)
+        .remove::<Enemy>()
+        .remove::<Brain>()
+        .remove::<Shooter>()
+        .remove::<Flyer>()
+        .remove::<Collider>()
+        .insert(RigidBody::Fixed)
+        .insert(Dying);
+}
+
+#[allow(clippy::type_complexity)]
+fn choose_clip(
+    mut animated: Query<
+        (&mut Animator, &Velocity, Option<&Jumper>, Option<&Flyer>),
+        Without<Dying>,
+    >,
+) {
+    for (mut animator, velocity, jumper, flyer) in animated.iter_mut() {
+        if animator.action {
+            if !animator.finished() {
+                continue;
+            }
+            animator.action = false;
+        }
+
+        let jumping = jumper.is_some_and(|jumper| jumper.is_jumping);
+        let clip = match flyer {
+            None if jumping && velocity.linvel.y > 0. => Clip::Jump,
+            None if velocity.linvel.y < -FALL_SPEED => Clip::Fall,
+            _ if velocity.linvel.x.abs() > RUN_SPEED => Clip::Run,
+            _ => Clip::Idle,
+        };
+        animator.switch_to(clip);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn animate_sprites(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut animated: Query<(
+        Entity,
+        &mut Animator,
+        &mut TextureAtlasSprite,
+        Option<&Player>,
+        Option<&Enemy>,
+        Option<&Dying>,
+    )>,
+) {
+    for (entity, mut animator, mut sprite, player, enemy, dying) in animated.iter_mut() {
+        animator.elapsed += time.delta_seconds();
+        sprite.index = animator.frame();
+
+        // sheets are drawn facing right
+        let direction = player
+            .map(|player| player.direction)
+            .or_else(|| enemy.map(|enemy| enemy.direction));
+        if let Some(direction) = direction {
+            sprite.flip_x = matches!(direction, GameDirection::Left);
+        }
+
+        if dying.is_some() && player.is_none() && animator.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
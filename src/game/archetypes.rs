@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use serde::Deserialize;
+
+use crate::game::{AiProfile, RonAssetPlugin, SpriteSheet};
+
+const ENEMY_ARCHETYPES_PATH: &str = "data/default.enemies.ron";
+
+pub struct ArchetypesPlugin;
+
+impl Plugin for ArchetypesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RonAssetPlugin::<EnemyArchetypes>::new("enemies.ron"))
+            .add_startup_system(load_enemy_archetypes)
+            .add_system(load_enemy_textures);
+    }
+}
+
+#[derive(Deserialize)]
+pub enum Movement {
+    Walking,
+    // flyers hover the given height above edges between columns of different heights
+    Flying {
+        hover_height: f32,
+        swoop_interval: f32,
+    },
+}
+
+#[derive(Deserialize)]
+pub struct EnemyWeapon {
+    pub cooldown: f32,
+}
+
+#[derive(Deserialize)]
+pub struct EnemyArchetype {
+    pub name: String,
+    pub sheet: SpriteSheet,
+    pub size: (f32, f32),
+    pub collider: (f32, f32),
+    pub speed: f32,
+    pub health: u32,
+    pub jump_impulse: f32,
+    pub ai: AiProfile,
+    pub ai_cadence: f32,
+    pub movement: Movement,
+    pub weapon: Option<EnemyWeapon>,
+    pub score: usize,
+}
+
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "8baab365-9ca6-4d36-9a2a-89b4ddb1923e"]
+pub struct EnemyArchetypes {
+    pub archetypes: Vec<EnemyArchetype>,
+}
+
+impl EnemyArchetypes {
+    pub fn get(&self, name: &str) -> Option<&EnemyArchetype> {
+        let archetype = self
+            .archetypes
+            .iter()
+            .find(|archetype| archetype.name == name);
+        if archetype.is_none() {
+            warn!("Unknown enemy archetype: {}", name);
+        }
+        archetype
+    }
+}
+
+// Atlases are kept here so that they stay loaded between levels
+pub struct EnemyAssets {
+    pub archetypes: Handle<EnemyArchetypes>,
+    pub atlases: HashMap<String, Handle<TextureAtlas>>,
+}
+
+impl EnemyAssets {
+    pub fn atlas(&self, archetype: &EnemyArchetype) -> Option<Handle<TextureAtlas>> {
+        let atlas = self.atlases.get(&archetype.sheet.texture).cloned();
+        if atlas.is_none() {
+            error!("No sprite sheet for enemy archetype: {}", archetype.name);
+        }
+        atlas
+    }
+}
+
+fn load_enemy_archetypes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(EnemyAssets {
+        archetypes: asset_server.load(ENEMY_ARCHETYPES_PATH),
+        atlases: HashMap::new(),
+    });
+}
+
+fn load_enemy_textures(
+    asset_server: Res<AssetServer>,
+    archetypes: Res<Assets<EnemyArchetypes>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut enemy_assets: ResMut<EnemyAssets>,
+    mut events: EventReader<AssetEvent<EnemyArchetypes>>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if let Some(archetypes) = archetypes.get(handle) {
+                for archetype in archetypes.archetypes.iter() {
+                    let texture = asset_server.load(archetype.sheet.texture.as_str());
+                    let atlas = texture_atlases.add(archetype.sheet.atlas(texture));
+                    enemy_assets
+                        .atlases
+                        .insert(archetype.sheet.texture.clone(), atlas);
+                }
+            }
+        }
+    }
+}
@@ -1,154 +1,314 @@
+use std::collections::HashMap;
+
 use bevy::prelude::*;
-use bevy_kira_audio::{Audio, AudioChannel, AudioPlugin, AudioSource};
+use bevy::reflect::TypeUuid;
+use bevy_kira_audio::{
+    Audio, AudioChannel, AudioPlugin, AudioSource, InstanceHandle, PlaybackState,
+};
+use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
+use serde::Deserialize;
 
+use crate::game::{CameraController, CurrentBiome, RonAssetPlugin};
+use crate::settings::{AudioBus, Settings};
 use crate::AppState;
 
+const SOUND_BANK_PATH: &str = "data/default.sounds.ron";
+// sound effects share this many channels, the oldest sound is cut off when all are busy
+const VOICES: usize = 16;
+// sounds with a position get quieter further than this from the camera, and aren't played
+// at all past the cutoff
+const FULL_VOLUME_DISTANCE: f32 = 10.0;
+const CUTOFF_DISTANCE: f32 = 30.0;
+// sounds this far to the side are panned as much as they can be, which never leaves one
+// ear silent
+const PAN_DISTANCE: f32 = 12.0;
+const MAX_PANNING: f32 = 0.8;
+// the music keeps playing under the pause and death menus, only quieter
+const DUCKED_MUSIC_VOLUME: f32 = 0.3;
+
 pub struct GameAudioPlugin;
 
-pub struct AudioHitEvent;
-pub struct AudioCoffeeEvent;
-pub struct AudioRustEvent;
-pub struct AudioFastShootEvent;
-pub struct AudioShootEvent;
-pub struct AudioDeadPlayerEvent;
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(AudioPlugin)
+            .add_plugin(RonAssetPlugin::<SoundBank>::new("sounds.ron"))
+            .init_resource::<CurrentMusic>()
+            .add_startup_system_to_stage(StartupStage::PreStartup, load_audio)
+            .add_system(load_sound_variants)
+            .add_system_set(SystemSet::on_update(AppState::InGame).with_system(play_bg_music))
+            .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(play_menu_music))
+            .add_system_set(SystemSet::on_enter(AppState::WinMenu).with_system(play_menu_music))
+            .add_system(apply_volumes)
+            .add_system(play_sfx)
+            .add_event::<PlaySfx>();
+    }
+}
+
+// Plays the sound with the given id from the sound bank
+pub struct PlaySfx {
+    pub id: &'static str,
+    // where in the level it happened, none for sounds of the menus
+    pub position: Option<Vec2>,
+}
+
+impl PlaySfx {
+    pub fn new(id: &'static str) -> Self {
+        PlaySfx { id, position: None }
+    }
+
+    pub fn at(id: &'static str, position: Vec3) -> Self {
+        PlaySfx {
+            id,
+            position: Some(position.truncate()),
+        }
+    }
+}
 
+#[derive(Deserialize)]
+pub struct Sound {
+    // one of the variants is picked each time, with a volume and pitch from the ranges
+    pub variants: Vec<String>,
+    pub volume: (f32, f32),
+    pub pitch: (f32, f32),
+    // the sound is skipped while this many of it are still playing
+    pub max_instances: usize,
+    #[serde(default)]
+    pub bus: AudioBus,
+}
+
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "5f0c9a6e-2b7d-4e13-a8c4-71d93e6b2f58"]
+pub struct SoundBank {
+    pub sounds: HashMap<String, Sound>,
+}
+
+impl SoundBank {
+    pub fn get(&self, id: &str) -> Option<&Sound> {
+        let sound = self.sounds.get(id);
+        if sound.is_none() {
+            warn!("Unknown sound: {}", id);
+        }
+        sound
+    }
+}
+
+// Channel a single sound effect plays in, so that it can have its own volume and pitch
+struct Voice {
+    channel: AudioChannel,
+    sound: &'static str,
+    instance: Option<InstanceHandle>,
+}
+
+impl Voice {
+    fn is_playing(&self, audio: &Audio) -> bool {
+        self.instance
+            .clone()
+            .is_some_and(|instance| !matches!(audio.state(instance), PlaybackState::Stopped))
+    }
+}
+
+// Variants are kept here so that they stay loaded between levels
 pub struct AudioAssets {
     bg: Handle<AudioSource>,
     menu: Handle<AudioSource>,
-    hit1: Handle<AudioSource>,
-    hit2: Handle<AudioSource>,
-    hit3: Handle<AudioSource>,
-    death: Handle<AudioSource>,
-    drink1: Handle<AudioSource>,
-    drink2: Handle<AudioSource>,
-    drink3: Handle<AudioSource>,
-    shoot: Handle<AudioSource>,
-    fast_shoot: Handle<AudioSource>,
-    lvlup: Handle<AudioSource>,
-    bg_channel: AudioChannel,
-    menu_channel: AudioChannel,
+    sound_bank: Handle<SoundBank>,
+    variants: HashMap<String, Handle<AudioSource>>,
+    music_channel: AudioChannel,
+    voices: Vec<Voice>,
+    next_voice: usize,
 }
 
-impl Plugin for GameAudioPlugin {
-    fn build(&self, app: &mut App) {
-        app.add_plugin(AudioPlugin)
-            .add_startup_system_to_stage(StartupStage::PreStartup, load_audio)
-            .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(play_bg_music))
-            .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(play_menu_music))
-            .add_system(play_hit_sfx)
-            .add_system(play_death_sfx)
-            .add_system(play_eat_sfx)
-            .add_system(play_lvlup_sfx)
-            .add_system(play_shoot_sfx)
-            .add_system(play_fast_shoot_sfx)
-            .add_startup_system(play_menu_music)
-            .add_event::<AudioRustEvent>()
-            .add_event::<AudioCoffeeEvent>()
-            .add_event::<AudioHitEvent>()
-            .add_event::<AudioFastShootEvent>()
-            .add_event::<AudioShootEvent>()
-            .add_event::<AudioDeadPlayerEvent>();
+impl AudioAssets {
+    // A voice that finished playing, or the one used longest ago if none did
+    fn take_voice(&mut self, audio: &Audio) -> &mut Voice {
+        let count = self.voices.len();
+        let index = (0..count)
+            .map(|i| (self.next_voice + i) % count)
+            .find(|&i| !self.voices[i].is_playing(audio))
+            .unwrap_or(self.next_voice);
+        self.next_voice = (index + 1) % count;
+        &mut self.voices[index]
     }
 }
 
-pub fn play_hit_sfx(
-    audio: Res<Audio>,
-    audio_state: Res<AudioAssets>,
-    mut audio_event: EventReader<AudioHitEvent>,
-) {
-    audio_event.iter().for_each(|_| {
-        let mut rng = thread_rng();
-        match rng.gen_range(0..100) {
-            // 1/3 chance of playing each sound
-            0..=33 => audio.play(audio_state.hit1.clone()),
-            34..=67 => audio.play(audio_state.hit2.clone()),
-            _ => audio.play(audio_state.hit3.clone()),
-        };
-    });
+// How loud a sound is and where it is heard from, for a sound at the given offset from the
+// camera. Nothing is heard past the cutoff.
+fn placement(offset: Vec2) -> Option<(f32, f32)> {
+    let distance = offset.length();
+    if distance > CUTOFF_DISTANCE {
+        return None;
+    }
+    let fade = (distance - FULL_VOLUME_DISTANCE) / (CUTOFF_DISTANCE - FULL_VOLUME_DISTANCE);
+    let attenuation = 1.0 - fade.clamp(0.0, 1.0);
+    let side = (offset.x / PAN_DISTANCE).clamp(-1.0, 1.0);
+    let panning = 0.5 + side * MAX_PANNING / 2.0;
+    Some((attenuation, panning))
 }
 
-pub fn play_eat_sfx(
+// Track the music channel is playing, so that moving between menus doesn't restart it
+#[derive(Default)]
+struct CurrentMusic(Option<Handle<AudioSource>>);
+
+fn play_sfx(
     audio: Res<Audio>,
-    audio_state: Res<AudioAssets>,
-    mut audio_event: EventReader<AudioCoffeeEvent>,
+    mut audio_assets: ResMut<AudioAssets>,
+    sound_banks: Res<Assets<SoundBank>>,
+    settings: Res<Settings>,
+    cameras: Query<&GlobalTransform, With<CameraController>>,
+    mut events: EventReader<PlaySfx>,
 ) {
-    audio_event.iter().for_each(|_| {
-        let mut rng = thread_rng();
-        match rng.gen_range(0..100) {
-            // 1/3 chance of playing each sound
-            0..=33 => audio.play(audio_state.drink1.clone()),
-            34..=67 => audio.play(audio_state.drink2.clone()),
-            _ => audio.play(audio_state.drink3.clone()),
+    let sound_bank = match sound_banks.get(&audio_assets.sound_bank) {
+        Some(sound_bank) => sound_bank,
+        None => return,
+    };
+
+    // without a camera, e.g. once the level is over, everything plays in the middle
+    let listener = cameras
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation.truncate());
+    let mut rng = thread_rng();
+    for event in events.iter() {
+        let (attenuation, panning) = match (event.position, listener) {
+            (Some(position), Some(listener)) => match placement(position - listener) {
+                Some(placement) => placement,
+                None => continue,
+            },
+            _ => (1.0, 0.5),
         };
-    });
+        let sound = match sound_bank.get(event.id) {
+            Some(sound) => sound,
+            None => continue,
+        };
+        let playing = audio_assets
+            .voices
+            .iter()
+            .filter(|voice| voice.sound == event.id && voice.is_playing(&audio))
+            .count();
+        if playing >= sound.max_instances {
+            continue;
+        }
+        let source = match sound
+            .variants
+            .choose(&mut rng)
+            .and_then(|variant| audio_assets.variants.get(variant))
+        {
+            Some(source) => source.clone(),
+            None => continue,
+        };
+
+        let volume =
+            settings.output_volume(sound.bus) * random_in(&mut rng, sound.volume) * attenuation;
+        let pitch = random_in(&mut rng, sound.pitch);
+        let voice = audio_assets.take_voice(&audio);
+        audio.stop_channel(&voice.channel);
+        audio.set_volume_in_channel(volume, &voice.channel);
+        audio.set_playback_rate_in_channel(pitch, &voice.channel);
+        audio.set_panning_in_channel(panning, &voice.channel);
+        voice.instance = Some(audio.play_in_channel(source, &voice.channel));
+        voice.sound = event.id;
+    }
+}
+
+fn random_in(rng: &mut impl Rng, (low, high): (f32, f32)) -> f32 {
+    low + (high - low) * rng.gen::<f32>()
 }
 
-pub fn play_lvlup_sfx(
+// Starts once the level picked its biome, the default track plays if the biome has none
+fn play_bg_music(
     audio: Res<Audio>,
     audio_state: Res<AudioAssets>,
-    mut audio_event: EventReader<AudioRustEvent>,
+    mut current: ResMut<CurrentMusic>,
+    biome: Option<Res<CurrentBiome>>,
 ) {
-    audio_event.iter().for_each(|_| {
-        audio.play(audio_state.lvlup.clone());
-    });
+    if let Some(biome) = biome.filter(|biome| biome.is_changed()) {
+        let music = biome
+            .music
+            .clone()
+            .unwrap_or_else(|| audio_state.bg.clone());
+        play_music(&audio, &audio_state, &mut current, music);
+    }
 }
 
-pub fn play_shoot_sfx(
+fn play_menu_music(
     audio: Res<Audio>,
     audio_state: Res<AudioAssets>,
-    mut audio_event: EventReader<AudioShootEvent>,
+    mut current: ResMut<CurrentMusic>,
 ) {
-    audio_event.iter().for_each(|_| {
-        audio.play(audio_state.shoot.clone());
-    });
+    play_music(&audio, &audio_state, &mut current, audio_state.menu.clone());
 }
 
-pub fn play_fast_shoot_sfx(
-    audio: Res<Audio>,
-    audio_state: Res<AudioAssets>,
-    mut audio_event: EventReader<AudioFastShootEvent>,
+fn play_music(
+    audio: &Audio,
+    audio_state: &AudioAssets,
+    current: &mut CurrentMusic,
+    music: Handle<AudioSource>,
 ) {
-    audio_event.iter().for_each(|_| {
-        audio.play(audio_state.fast_shoot.clone());
-    });
+    if current.0.as_ref() != Some(&music) {
+        audio.stop_channel(&audio_state.music_channel);
+        audio.play_looped_in_channel(music.clone(), &audio_state.music_channel);
+        current.0 = Some(music);
+    }
 }
 
-pub fn play_death_sfx(
+// The music channel keeps its volume for the tracks played later, so it's only set when it
+// changes. Sound effects get theirs when they start.
+fn apply_volumes(
     audio: Res<Audio>,
     audio_state: Res<AudioAssets>,
-    mut death_events: EventReader<AudioDeadPlayerEvent>,
+    settings: Res<Settings>,
+    state: Res<State<AppState>>,
 ) {
-    for _ in death_events.iter() {
-        audio.play(audio_state.death.clone());
+    if !settings.is_changed() && !state.is_changed() {
+        return;
     }
-}
-
-fn play_bg_music(audio: Res<Audio>, audio_state: Res<AudioAssets>) {
-    audio.stop_channel(&audio_state.menu_channel);
-    audio.play_looped_in_channel(audio_state.bg.clone(), &audio_state.bg_channel);
-}
 
-fn play_menu_music(audio: Res<Audio>, audio_state: Res<AudioAssets>) {
-    audio.stop_channel(&audio_state.bg_channel);
-    audio.play_looped_in_channel(audio_state.menu.clone(), &audio_state.menu_channel);
+    let ducking = match state.current() {
+        AppState::StopMenu | AppState::FailMenu => DUCKED_MUSIC_VOLUME,
+        _ => 1.0,
+    };
+    audio.set_volume_in_channel(
+        settings.output_volume(AudioBus::Music) * ducking,
+        &audio_state.music_channel,
+    );
 }
 
 fn load_audio(mut commands: Commands, assets: Res<AssetServer>) {
     commands.insert_resource(AudioAssets {
         bg: assets.load("audio/background.ogg"),
         menu: assets.load("audio/menu.ogg"),
-        hit1: assets.load("audio/hit1.ogg"),
-        hit2: assets.load("audio/hit2.ogg"),
-        hit3: assets.load("audio/hit3.ogg"),
-        death: assets.load("audio/death.ogg"),
-        drink1: assets.load("audio/drink1.ogg"),
-        drink2: assets.load("audio/drink2.ogg"),
-        drink3: assets.load("audio/drink3.ogg"),
-        shoot: assets.load("audio/shoot.ogg"),
-        fast_shoot: assets.load("audio/fast_shoot.ogg"),
-        lvlup: assets.load("audio/levelup.ogg"),
-        bg_channel: AudioChannel::new("bg".to_string()),
-        menu_channel: AudioChannel::new("menu".to_string()),
+        sound_bank: assets.load(SOUND_BANK_PATH),
+        variants: HashMap::new(),
+        music_channel: AudioChannel::new("music".to_string()),
+        voices: (0..VOICES)
+            .map(|i| Voice {
+                channel: AudioChannel::new(format!("sfx{}", i)),
+                sound: "",
+                instance: None,
+            })
+            .collect(),
+        next_voice: 0,
     });
 }
+
+fn load_sound_variants(
+    asset_server: Res<AssetServer>,
+    sound_banks: Res<Assets<SoundBank>>,
+    mut audio_assets: ResMut<AudioAssets>,
+    mut events: EventReader<AssetEvent<SoundBank>>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if let Some(sound_bank) = sound_banks.get(handle) {
+                for sound in sound_bank.sounds.values() {
+                    for variant in sound.variants.iter() {
+                        audio_assets
+                            .variants
+                            .insert(variant.clone(), asset_server.load(variant.as_str()));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy_kira_audio::AudioSource;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::game::{EnemySpawn, LevelBand, ParallaxLayerSpec, RonAssetPlugin};
+use crate::{AppState, GameTextures, Random};
+
+const BIOMES_PATH: &str = "data/default.biomes.ron";
+const MENU_BACKGROUND: Color = Color::BEIGE;
+
+pub struct BiomesPlugin;
+
+impl Plugin for BiomesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RonAssetPlugin::<Biomes>::new("biomes.ron"))
+            .add_startup_system(load_biomes)
+            .add_system(load_biome_assets)
+            .add_system_set(SystemSet::on_update(AppState::InGame).with_system(apply_biome))
+            .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(reset_background));
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Biome {
+    pub name: String,
+    pub tileset: String,
+    pub background: (f32, f32, f32),
+    pub music: String,
+    // enemies spawned on top of the ones from the level band
+    #[serde(default)]
+    pub enemies: Vec<EnemySpawn>,
+    #[serde(default)]
+    pub layers: Vec<ParallaxLayerSpec>,
+}
+
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "b3d6f0e2-7a41-4c8e-9f15-6d2a8e4c1b07"]
+pub struct Biomes {
+    pub biomes: Vec<Biome>,
+}
+
+impl Biomes {
+    // The biome is picked with the level generator, so the same seed always gives the same
+    // biomes. Bands listing no biomes can use any of them.
+    pub fn choose(&self, band: Option<&LevelBand>, rng: &mut Random) -> Option<&Biome> {
+        let candidates: Vec<&Biome> = self
+            .biomes
+            .iter()
+            .filter(|biome| {
+                band.is_none_or(|band| band.biomes.is_empty() || band.biomes.contains(&biome.name))
+            })
+            .collect();
+        if candidates.is_empty() {
+            warn!("No biome matches the level band");
+            return None;
+        }
+        Some(candidates[rng.generator.gen_range(0..candidates.len())])
+    }
+}
+
+// Textures and music are kept here so that they stay loaded between levels
+pub struct BiomeAssets {
+    pub biomes: Handle<Biomes>,
+    pub textures: HashMap<String, Handle<Image>>,
+    pub music: HashMap<String, Handle<AudioSource>>,
+}
+
+// Biome of the level being played
+pub struct CurrentBiome {
+    pub tileset: Handle<Image>,
+    pub background: Color,
+    pub music: Option<Handle<AudioSource>>,
+}
+
+impl CurrentBiome {
+    pub fn new(biome: &Biome, biome_assets: &BiomeAssets, game_textures: &GameTextures) -> Self {
+        let (r, g, b) = biome.background;
+        CurrentBiome {
+            tileset: biome_assets
+                .textures
+                .get(&biome.tileset)
+                .cloned()
+                .unwrap_or_else(|| game_textures.tileset.clone()),
+            background: Color::rgb(r, g, b),
+            music: biome_assets.music.get(&biome.music).cloned(),
+        }
+    }
+
+    // Used when the biomes aren't loaded, looks like the game always did
+    pub fn fallback(game_textures: &GameTextures) -> Self {
+        CurrentBiome {
+            tileset: game_textures.tileset.clone(),
+            background: MENU_BACKGROUND,
+            music: None,
+        }
+    }
+}
+
+fn load_biomes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(BiomeAssets {
+        biomes: asset_server.load(BIOMES_PATH),
+        textures: HashMap::new(),
+        music: HashMap::new(),
+    });
+}
+
+fn load_biome_assets(
+    asset_server: Res<AssetServer>,
+    biomes: Res<Assets<Biomes>>,
+    mut biome_assets: ResMut<BiomeAssets>,
+    mut events: EventReader<AssetEvent<Biomes>>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if let Some(biomes) = biomes.get(handle) {
+                for biome in biomes.biomes.iter() {
+                    let texture = asset_server.load(biome.tileset.as_str());
+                    biome_assets.textures.insert(biome.tileset.clone(), texture);
+                    for layer in biome.layers.iter() {
+                        let texture = asset_server.load(layer.texture.as_str());
+                        biome_assets.textures.insert(layer.texture.clone(), texture);
+                    }
+                    let music = asset_server.load(biome.music.as_str());
+                    biome_assets.music.insert(biome.music.clone(), music);
+                }
+            }
+        }
+    }
+}
+
+fn apply_biome(biome: Option<Res<CurrentBiome>>, mut clear_color: ResMut<ClearColor>) {
+    if let Some(biome) = biome.filter(|biome| biome.is_changed()) {
+        clear_color.0 = biome.background;
+    }
+}
+
+fn reset_background(mut clear_color: ResMut<ClearColor>) {
+    clear_color.0 = MENU_BACKGROUND;
+}
@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+use crate::game::{
+    add_colliders, spawn_coffee, spawn_ects, spawn_rust, Player, StrongBullet, SAFE_ZONE_WIDTH,
+};
+use crate::{AppState, GameTextures, Level, Random};
+
+use super::utils::*;
+
+const BLOCK_ROW_PROBABILITY: f64 = 0.06;
+const BLOCK_ROW_HEIGHT: usize = 3;
+const MIN_BLOCK_ROW_LENGTH: usize = 2;
+const MAX_BLOCK_ROW_LENGTH: usize = 5;
+const QUESTION_BLOCK_PROBABILITY: f64 = 0.3;
+const SECRET_ROUTE_PROBABILITY: f64 = 0.02;
+const SECRET_STEP_WIDTH: i32 = 2;
+const SECRET_STEP_HEIGHT: usize = 2;
+const SECRET_ROUTE_ECTS: i32 = 3;
+const BLOCK_MARGIN: i32 = 5;
+const MIN_BLOCK_DISTANCE: i32 = 6;
+const REWARD_HEIGHT: f32 = 1.0;
+
+pub struct BlocksPlugin;
+
+impl Plugin for BlocksPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(AppState::InGame).with_system(hit_blocks));
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum BlockReward {
+    Coffee,
+    Rust,
+    Ects,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum BlockKind {
+    // broken by a strong bullet or a head-bump
+    Breakable,
+    // releases its reward when hit from below
+    Question(BlockReward),
+    // invisible and not solid until the player jumps into it from below
+    Hidden,
+    // an emptied question block or a revealed hidden one
+    Used,
+}
+
+impl BlockKind {
+    fn is_solid(&self) -> bool {
+        *self != BlockKind::Hidden
+    }
+}
+
+// Block tile, `row` is the entity holding its `BlockRow`
+#[derive(Component)]
+pub struct Block {
+    pub row: Entity,
+}
+
+// Blocks lying side by side at the same height share their colliders, which are laid out
+// again whenever a block appears or disappears
+#[derive(Component)]
+pub struct BlockRow {
+    pub y: usize,
+    pub blocks: BTreeMap<i32, (Entity, BlockKind)>,
+    pub colliders: Vec<Entity>,
+}
+
+#[derive(Component)]
+pub struct RowCollider {
+    pub row: Entity,
+}
+
+enum BlockHit {
+    HeadBump,
+    Bullet,
+}
+
+fn block_texture(game_textures: &GameTextures, kind: BlockKind) -> Handle<Image> {
+    match kind {
+        BlockKind::Breakable => game_textures.brick.clone(),
+        BlockKind::Question(_) => game_textures.question_block.clone(),
+        BlockKind::Hidden | BlockKind::Used => game_textures.used_block.clone(),
+    }
+}
+
+fn spawn_block_row(
+    commands: &mut Commands,
+    game_textures: &Res<GameTextures>,
+    y: usize,
+    blocks: &[(i32, BlockKind)],
+) {
+    let row = commands.spawn().id();
+    let blocks = blocks
+        .iter()
+        .map(|&(x, kind)| {
+            let block = spawn_static_object(
+                commands,
+                create_sprite_bundle(
+                    block_texture(game_textures, kind),
+                    (1.0, 1.0),
+                    (x as f32, y as f32, 0.),
+                ),
+            );
+            commands.entity(block).insert(Block { row });
+            if kind == BlockKind::Hidden {
+                spawn_sensor_collider(commands, block, Collider::cuboid(0.5, 0.5));
+                commands
+                    .entity(block)
+                    .insert(Visibility { is_visible: false });
+            }
+            (x, (block, kind))
+        })
+        .collect();
+
+    let mut block_row = BlockRow {
+        y,
+        blocks,
+        colliders: Vec::new(),
+    };
+    lay_out_row(commands, row, &mut block_row);
+    commands.entity(row).insert(block_row);
+}
+
+pub fn despawn_block_row(commands: &mut Commands, row: Entity, block_row: &BlockRow) {
+    for &(block, _) in block_row.blocks.values() {
+        commands.entity(block).despawn_recursive();
+    }
+    for &collider in block_row.colliders.iter() {
+        commands.entity(collider).despawn();
+    }
+    commands.entity(row).despawn();
+}
+
+fn lay_out_row(commands: &mut Commands, row: Entity, block_row: &mut BlockRow) {
+    for collider in block_row.colliders.drain(..) {
+        commands.entity(collider).despawn();
+    }
+
+    let solid: Vec<(i32, usize)> = block_row
+        .blocks
+        .iter()
+        .filter(|(_, (_, kind))| kind.is_solid())
+        .map(|(&x, _)| (x, block_row.y))
+        .collect();
+    if solid.is_empty() {
+        return;
+    }
+
+    block_row.colliders = add_colliders(&solid, block_row.y as f32 - 0.5, commands);
+    for &collider in block_row.colliders.iter() {
+        commands.entity(collider).insert(RowCollider { row });
+    }
+}
+
+// Rows of blocks float over flat pieces of the map, with a few hidden steps leading to
+// secret ECTS
+pub fn add_blocks(
+    commands: &mut Commands,
+    world: &[(i32, usize)],
+    game_textures: &Res<GameTextures>,
+    rng: &mut Random,
+    level: &Res<Level>,
+) {
+    let mut last_block = i32::MIN;
+
+    world.windows(MAX_BLOCK_ROW_LENGTH).for_each(|window| {
+        let (x, height) = window[0];
+        let flat = window
+            .iter()
+            .enumerate()
+            .all(|(i, &(column, h))| column == x + i as i32 && h == height);
+        if !flat
+            || x <= SAFE_ZONE_WIDTH as i32 + BLOCK_MARGIN
+            || x >= level.finish_x() - BLOCK_MARGIN
+            || x - last_block < MIN_BLOCK_DISTANCE
+        {
+            return;
+        }
+
+        if rng.generator.gen_bool(SECRET_ROUTE_PROBABILITY) {
+            last_block = x + MAX_BLOCK_ROW_LENGTH as i32;
+            add_secret_route(commands, game_textures, x, height);
+        } else if rng.generator.gen_bool(BLOCK_ROW_PROBABILITY) {
+            let length = rng
+                .generator
+                .gen_range(MIN_BLOCK_ROW_LENGTH..=MAX_BLOCK_ROW_LENGTH);
+            let blocks: Vec<(i32, BlockKind)> = (0..length as i32)
+                .map(|i| (x + i, random_block_kind(rng)))
+                .collect();
+            last_block = x + length as i32;
+            spawn_block_row(commands, game_textures, height + BLOCK_ROW_HEIGHT, &blocks);
+        }
+    });
+}
+
+fn random_block_kind(rng: &mut Random) -> BlockKind {
+    if !rng.generator.gen_bool(QUESTION_BLOCK_PROBABILITY) {
+        return BlockKind::Breakable;
+    }
+    BlockKind::Question(match rng.generator.gen_range(0..10) {
+        0..=5 => BlockReward::Ects,
+        6..=8 => BlockReward::Coffee,
+        _ => BlockReward::Rust,
+    })
+}
+
+// Two hidden steps going up to the right, with ECTS on top of the second one
+fn add_secret_route(
+    commands: &mut Commands,
+    game_textures: &Res<GameTextures>,
+    x: i32,
+    height: usize,
+) {
+    let first_step = height + BLOCK_ROW_HEIGHT;
+    let second_step = first_step + SECRET_STEP_HEIGHT;
+    spawn_block_row(
+        commands,
+        game_textures,
+        first_step,
+        &[(x, BlockKind::Hidden)],
+    );
+    spawn_block_row(
+        commands,
+        game_textures,
+        second_step,
+        &[(x + SECRET_STEP_WIDTH, BlockKind::Hidden)],
+    );
+    (0..SECRET_ROUTE_ECTS).for_each(|i| {
+        spawn_ects(
+            commands,
+            game_textures,
+            (x + SECRET_STEP_WIDTH + i) as f32,
+            second_step as f32 + REWARD_HEIGHT,
+        );
+    });
+}
+
+// Head-bumps hit the block right above the player, strong bullets the breakable block closest
+// to the point of impact. Hidden blocks are revealed by jumping into them.
+#[allow(clippy::too_many_arguments)]
+fn hit_blocks(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    players: Query<(Entity, &Transform, &Velocity), With<Player>>,
+    bullets: Query<(Entity, &Transform), With<StrongBullet>>,
+    row_colliders: Query<&RowCollider>,
+    hidden_blocks: Query<(&Block, &Transform)>,
+    mut rows: Query<&mut BlockRow>,
+    mut collision_events: EventReader<CollisionEvent>,
+) {
+    let mut hits = Vec::new();
+    for collision_event in collision_events.iter() {
+        if let CollisionEvent::Started(ent1, ent2, _) = collision_event {
+            match (
+                players.get(*ent1),
+                row_colliders.get(*ent2),
+                players.get(*ent2),
+                row_colliders.get(*ent1),
+            ) {
+                (Ok((_, player, _)), Ok(collider), _, _)
+                | (_, _, Ok((_, player, _)), Ok(collider)) => {
+                    hits.push((collider.row, player.translation, BlockHit::HeadBump));
+                }
+                _ => {}
+            }
+
+            match (
+                bullets.get(*ent1),
+                row_colliders.get(*ent2),
+                bullets.get(*ent2),
+                row_colliders.get(*ent1),
+            ) {
+                (Ok((_, bullet)), Ok(collider), _, _) | (_, _, Ok((_, bullet)), Ok(collider)) => {
+                    hits.push((collider.row, bullet.translation, BlockHit::Bullet));
+                }
+                _ => {}
+            }
+
+            match (
+                players.get(*ent1),
+                hidden_blocks.get(*ent2),
+                players.get(*ent2),
+                hidden_blocks.get(*ent1),
+            ) {
+                (Ok((_, player, velocity)), Ok((block, transform)), _, _)
+                | (_, _, Ok((_, player, velocity)), Ok((block, transform)))
+                    if velocity.linvel.y > 0. =>
+                {
+                    let position = Vec3::new(transform.translation.x, player.translation.y, 0.);
+                    hits.push((block.row, position, BlockHit::HeadBump));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for (row, position, hit) in hits {
+        if let Ok(mut block_row) = rows.get_mut(row) {
+            // only hits from below count as head-bumps
+            let y = block_row.y as f32;
+            if matches!(hit, BlockHit::HeadBump) && position.y >= y - 0.5 {
+                continue;
+            }
+            // bullets touch the side of a row half a column before its end block
+            let x = position.x.round() as i32;
+            let reach = match hit {
+                BlockHit::HeadBump => 0,
+                BlockHit::Bullet => 1,
+            };
+            let target = block_row
+                .blocks
+                .iter()
+                .filter(|(&column, _)| (column - x).abs() <= reach)
+                .min_by_key(|(&column, _)| (column - x).abs())
+                .map(|(&column, &block)| (column, block));
+
+            let (column, (block, kind)) = match target {
+                Some(target) => target,
+                None => continue,
+            };
+            // bullets only break bricks, whatever else they hit stops them
+            if matches!(hit, BlockHit::Bullet) && kind != BlockKind::Breakable {
+                continue;
+            }
+            match kind {
+                BlockKind::Breakable => {
+                    commands.entity(block).despawn_recursive();
+                    block_row.blocks.remove(&column);
+                }
+                BlockKind::Question(reward) => {
+                    let (x, y) = (column as f32, y + REWARD_HEIGHT);
+                    match reward {
+                        BlockReward::Coffee => spawn_coffee(&mut commands, &game_textures, x, y),
+                        BlockReward::Rust => spawn_rust(&mut commands, &game_textures, x, y),
+                        BlockReward::Ects => spawn_ects(&mut commands, &game_textures, x, y),
+                    }
+                    commands
+                        .entity(block)
+                        .insert(game_textures.used_block.clone());
+                    block_row.blocks.insert(column, (block, BlockKind::Used));
+                    continue;
+                }
+                BlockKind::Hidden => {
+                    commands
+                        .entity(block)
+                        .remove::<Collider>()
+                        .remove::<Sensor>()
+                        .insert(Visibility { is_visible: true });
+                    block_row.blocks.insert(column, (block, BlockKind::Used));
+                }
+                BlockKind::Used => continue,
+            }
+            // the block appeared or disappeared, so the row needs new colliders
+            lay_out_row(&mut commands, row, &mut block_row);
+        }
+    }
+}
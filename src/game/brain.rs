@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AiState {
+    Patrol,
+    Chase,
+    Attack,
+    Flee,
+    Stunned,
+}
+
+// Behaviours an enemy type is made of. Every enabled behaviour adds a state the enemy can be in,
+// the ones left as None are never entered.
+#[derive(Copy, Clone, Deserialize)]
+pub struct AiProfile {
+    pub jump_probability: f64,
+    pub change_direction_probability: f64,
+    pub chase_radius: Option<f32>,
+    pub attack_range: Option<f32>,
+    pub flee_radius: Option<f32>,
+    pub stun_duration: f32,
+}
+
+// What an enemy knows about the player at the moment
+pub struct Perception {
+    pub distance_to_player: Option<f32>,
+    pub sees_player: bool,
+    pub player_is_dangerous: bool,
+}
+
+#[derive(Component)]
+pub struct Brain {
+    pub profile: AiProfile,
+    pub state: AiState,
+    stun_timer: Timer,
+}
+
+impl Brain {
+    pub fn new(profile: AiProfile) -> Self {
+        Brain {
+            profile,
+            state: AiState::Patrol,
+            stun_timer: Timer::from_seconds(profile.stun_duration, false),
+        }
+    }
+
+    pub fn is(&self, state: AiState) -> bool {
+        self.state == state
+    }
+
+    pub fn stun(&mut self) {
+        self.stun_timer.reset();
+        self.state = AiState::Stunned;
+    }
+
+    pub fn update(&mut self, delta: Duration, perception: &Perception) {
+        if self.is(AiState::Stunned) && !self.stun_timer.tick(delta).finished() {
+            return;
+        }
+
+        self.state = self.next_state(perception);
+    }
+
+    fn next_state(&self, perception: &Perception) -> AiState {
+        let distance = match perception.distance_to_player {
+            Some(distance) => distance,
+            None => return AiState::Patrol,
+        };
+        let in_radius = |radius: Option<f32>| radius.is_some_and(|radius| distance < radius);
+
+        if perception.player_is_dangerous && in_radius(self.profile.flee_radius) {
+            AiState::Flee
+        } else if perception.sees_player && in_radius(self.profile.attack_range) {
+            AiState::Attack
+        } else if perception.sees_player && in_radius(self.profile.chase_radius) {
+            AiState::Chase
+        } else {
+            AiState::Patrol
+        }
+    }
+}
@@ -4,18 +4,20 @@ use bevy_rapier2d::prelude::*;
 use super::utils::*;
 use super::GameDirection;
 use crate::game::{
-    spawn_dynamic_object, AudioHitEvent, Bullet, Enemy, EnemyBullet, PlayersBullet, Wall,
+    spawn_dynamic_object, start_dying, Animator, Brain, Bullet, CameraEffectEvent, Clip, Enemy,
+    EnemyBullet, ParticleEffect, ParticleEvent, PlaySfx, PlayersBullet, RunStats, StrongBullet,
+    Wall, WeakBullet,
 };
 use crate::GameTextures;
 
 pub struct BulletsPlugin;
 
-pub struct ShootEvent;
-pub struct FastShootEvent;
-
 const WEAK_BULLET_SPEED: f32 = 8.25;
 const STRONG_BULLET_SPEED: f32 = 18.5;
 const ENEMY_BULLET_SPEED: f32 = 8.25;
+const WEAK_BULLET_DAMAGE: u32 = 1;
+const STRONG_BULLET_DAMAGE: u32 = 2;
+const MUZZLE_OFFSET: f32 = 0.75;
 
 #[derive(Copy, Clone)]
 pub struct BulletOptions {
@@ -25,15 +27,24 @@ pub struct BulletOptions {
     pub player_vex: f32,
 }
 
+impl BulletOptions {
+    // Bullets appear in front of the shooter, not inside it
+    pub fn muzzle(&self) -> Vec3 {
+        let offset = match self.direction {
+            GameDirection::Left => -MUZZLE_OFFSET,
+            GameDirection::Right => MUZZLE_OFFSET,
+        };
+        Vec3::new(self.x + offset, self.y, 0.0)
+    }
+}
+
 impl Plugin for BulletsPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(
             SystemSet::new()
                 .with_system(destroy_bullet_on_contact)
                 .with_system(kill_enemy),
-        )
-        .add_event::<ShootEvent>()
-        .add_event::<FastShootEvent>();
+        );
     }
 }
 
@@ -43,13 +54,14 @@ fn spawn_bullet(
     options: BulletOptions,
     def_vel: f32,
 ) -> Entity {
-    let (vel_x, spawn_x) = match options.direction {
-        GameDirection::Left => (-def_vel, -0.75),
-        GameDirection::Right => (def_vel, 0.75),
+    let vel_x = match options.direction {
+        GameDirection::Left => -def_vel,
+        GameDirection::Right => def_vel,
     };
+    let muzzle = options.muzzle();
     let mut bullet_entity = spawn_dynamic_object(
         commands,
-        create_sprite_bundle(texture, (0.5, 0.2), (options.x + spawn_x, options.y, 0.0)),
+        create_sprite_bundle(texture, (0.5, 0.2), (muzzle.x, muzzle.y, muzzle.z)),
         Some(vel_x),
         Some(0.0),
     );
@@ -72,7 +84,10 @@ pub fn spawn_strong_bullet(
         options,
         STRONG_BULLET_SPEED,
     );
-    commands.entity(bullet).insert(PlayersBullet);
+    commands
+        .entity(bullet)
+        .insert(PlayersBullet)
+        .insert(StrongBullet);
 }
 
 pub fn spawn_weak_bullet(
@@ -86,13 +101,18 @@ pub fn spawn_weak_bullet(
         options,
         WEAK_BULLET_SPEED,
     );
-    commands.entity(bullet).insert(PlayersBullet);
+    commands
+        .entity(bullet)
+        .insert(PlayersBullet)
+        .insert(WeakBullet);
 }
 
+// The angle is measured in radians upwards from the shooting direction
 pub fn spawn_enemy_bullet(
     commands: &mut Commands,
     game_textures: &Res<GameTextures>,
     options: BulletOptions,
+    angle: f32,
 ) {
     let bullet = spawn_bullet(
         commands,
@@ -100,14 +120,23 @@ pub fn spawn_enemy_bullet(
         options,
         ENEMY_BULLET_SPEED,
     );
-    commands.entity(bullet).insert(EnemyBullet);
+    let (sin, cos) = angle.sin_cos();
+    let vel_x = match options.direction {
+        GameDirection::Left => -cos,
+        GameDirection::Right => cos,
+    };
+    commands
+        .entity(bullet)
+        .insert(Velocity::linear(Vec2::new(vel_x, sin) * ENEMY_BULLET_SPEED))
+        .insert(EnemyBullet);
 }
 
 pub fn destroy_bullet_on_contact(
     mut commands: Commands,
-    bullets: Query<Entity, With<Bullet>>,
+    bullets: Query<(Entity, &Transform), With<Bullet>>,
     walls: Query<Entity, With<Wall>>,
     mut collision_events: EventReader<CollisionEvent>,
+    mut send_particle_event: EventWriter<ParticleEvent>,
 ) {
     for collision_event in collision_events.iter() {
         if let CollisionEvent::Started(ent1, ent2, _) = collision_event {
@@ -117,8 +146,12 @@ pub fn destroy_bullet_on_contact(
                 bullets.get(*ent2),
                 walls.get(*ent1),
             ) {
-                (Ok(bullet), Ok(_), _, _) | (_, _, Ok(bullet), Ok(_)) => {
-                    commands.entity(bullet).despawn_recursive()
+                (Ok((bullet, transform)), Ok(_), _, _) | (_, _, Ok((bullet, transform)), Ok(_)) => {
+                    commands.entity(bullet).despawn_recursive();
+                    send_particle_event.send(ParticleEvent::new(
+                        ParticleEffect::ImpactSparks,
+                        transform.translation,
+                    ));
                 }
                 _ => {}
             }
@@ -126,12 +159,23 @@ pub fn destroy_bullet_on_contact(
     }
 }
 
+// Enemies surviving a hit are stunned for a moment
+#[allow(clippy::too_many_arguments)]
 pub fn kill_enemy(
     mut commands: Commands,
-    bullets: Query<Entity, With<PlayersBullet>>,
+    bullets: Query<(Entity, Option<&StrongBullet>), With<PlayersBullet>>,
     enemies: Query<Entity, With<Enemy>>,
+    mut hit_enemies: Query<(
+        &mut Enemy,
+        &Transform,
+        Option<&mut Brain>,
+        Option<&mut Animator>,
+    )>,
+    mut stats: ResMut<RunStats>,
     mut collision_event: EventReader<CollisionEvent>,
-    mut send_audio_hit_event: EventWriter<AudioHitEvent>,
+    mut send_sfx: EventWriter<PlaySfx>,
+    mut send_camera_effect_event: EventWriter<CameraEffectEvent>,
+    mut send_particle_event: EventWriter<ParticleEvent>,
 ) {
     for collision_event in collision_event.iter() {
         if let CollisionEvent::Started(ent1, ent2, _) = collision_event {
@@ -141,10 +185,52 @@ pub fn kill_enemy(
                 bullets.get(*ent2),
                 enemies.get(*ent1),
             ) {
-                (Ok(bullet), Ok(enemy), _, _) | (_, _, Ok(bullet), Ok(enemy)) => {
-                    send_audio_hit_event.send(AudioHitEvent);
+                (Ok((bullet, strong)), Ok(enemy), _, _)
+                | (_, _, Ok((bullet, strong)), Ok(enemy)) => {
                     commands.entity(bullet).despawn_recursive();
-                    commands.entity(enemy).despawn_recursive();
+
+                    let damage = match strong {
+                        Some(_) => STRONG_BULLET_DAMAGE,
+                        None => WEAK_BULLET_DAMAGE,
+                    };
+                    if let Ok((mut monster, transform, brain, animator)) =
+                        hit_enemies.get_mut(enemy)
+                    {
+                        // removing the enemy waits for the end of the frame, another bullet
+                        // can hit it before that
+                        if monster.health == 0 {
+                            continue;
+                        }
+                        send_sfx.send(PlaySfx::at("hit", transform.translation));
+                        if monster.health <= damage {
+                            monster.health = 0;
+                            stats.add_score(monster.score);
+                            send_particle_event.send(ParticleEvent::new(
+                                ParticleEffect::DeathBurst,
+                                transform.translation,
+                            ));
+                            match animator {
+                                Some(mut animator) => {
+                                    start_dying(&mut commands, enemy, &mut animator)
+                                }
+                                None => commands.entity(enemy).despawn_recursive(),
+                            }
+                            send_camera_effect_event.send(CameraEffectEvent::Kill);
+                        } else {
+                            monster.health -= damage;
+                            send_particle_event.send(ParticleEvent::new(
+                                ParticleEffect::ImpactSparks,
+                                transform.translation,
+                            ));
+                            send_camera_effect_event.send(CameraEffectEvent::Hit);
+                            if let Some(mut brain) = brain {
+                                brain.stun();
+                            }
+                            if let Some(mut animator) = animator {
+                                animator.play(Clip::Hurt);
+                            }
+                        }
+                    }
                 }
                 _ => {}
             }
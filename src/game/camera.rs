@@ -1,13 +1,46 @@
 use bevy::prelude::*;
 use bevy::render::camera::Camera2d;
 use bevy::{
-    math::Vec3,
     prelude::OrthographicCameraBundle,
     render::camera::{DepthCalculation, OrthographicProjection, ScalingMode},
 };
-use bevy_rapier2d::prelude::RigidBody;
 
-use crate::game::Player;
+use crate::game::{GameDirection, Player};
+
+// the camera always shows at least this many tiles, whatever the shape of the window
+const MIN_VISIBLE_WIDTH: f32 = 20.0;
+const MIN_VISIBLE_HEIGHT: f32 = 12.5;
+const CAMERA_SMOOTHING: f32 = 6.0;
+const DEAD_ZONE_HALF_WIDTH: f32 = 1.0;
+// jumps don't move the camera, but falls do
+const DEAD_ZONE_ABOVE: f32 = 2.5;
+const DEAD_ZONE_BELOW: f32 = 0.5;
+const LOOK_AHEAD_DISTANCE: f32 = 3.0;
+const LOOK_AHEAD_SMOOTHING: f32 = 2.0;
+// the player is kept below the middle of the view, so there's more room to see platforms
+const VERTICAL_OFFSET: f32 = 1.5;
+
+// Part of the world the camera is allowed to show, set by the map generator
+pub struct LevelBounds {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+#[derive(Component, Default)]
+pub struct CameraController {
+    focus: Option<Vec2>,
+    look_ahead: f32,
+    position: Option<Vec2>,
+}
+
+impl CameraController {
+    // Where the camera is without the screen effects
+    pub fn position(&self) -> Option<Vec2> {
+        self.position
+    }
+}
 
 pub fn new_camera_2d() -> OrthographicCameraBundle<Camera2d> {
     let far = 1000.0;
@@ -15,21 +48,93 @@ pub fn new_camera_2d() -> OrthographicCameraBundle<Camera2d> {
     camera.orthographic_projection = OrthographicProjection {
         far,
         depth_calculation: DepthCalculation::ZDifference,
-        scaling_mode: ScalingMode::FixedHorizontal,
+        scaling_mode: ScalingMode::None,
         ..Default::default()
     };
-    camera.transform.scale = Vec3::new(10., 10., 1.);
     camera
 }
 
+// Wider windows see more of the level to the sides, taller ones more above and below
+pub fn fit_camera_to_window(
+    windows: Res<Windows>,
+    mut cameras: Query<&mut OrthographicProjection, With<CameraController>>,
+) {
+    let aspect_ratio = match windows.get_primary() {
+        Some(window) if window.height() > 0. => window.width() / window.height(),
+        _ => return,
+    };
+    let (width, height) = if aspect_ratio > MIN_VISIBLE_WIDTH / MIN_VISIBLE_HEIGHT {
+        (MIN_VISIBLE_HEIGHT * aspect_ratio, MIN_VISIBLE_HEIGHT)
+    } else {
+        (MIN_VISIBLE_WIDTH, MIN_VISIBLE_WIDTH / aspect_ratio)
+    };
+
+    for mut projection in cameras.iter_mut() {
+        if projection.right != width / 2. || projection.top != height / 2. {
+            projection.left = -width / 2.;
+            projection.right = width / 2.;
+            projection.bottom = -height / 2.;
+            projection.top = height / 2.;
+        }
+    }
+}
+
+// The camera focus only moves once the player leaves the dead-zone around it, so small
+// corrections of the player's position don't shake the view
 pub fn camera_follow_player(
-    mut cameras: Query<&mut Transform, With<Camera>>,
-    players: Query<(&Transform, &RigidBody, &Player), Without<Camera>>,
+    time: Res<Time>,
+    bounds: Option<Res<LevelBounds>>,
+    mut cameras: Query<(
+        &mut Transform,
+        &OrthographicProjection,
+        &mut CameraController,
+    )>,
+    players: Query<(&Transform, &Player), Without<CameraController>>,
 ) {
-    if let Ok((player, _, _)) = players.get_single() {
-        for mut camera in cameras.iter_mut() {
-            camera.translation.x = player.translation.x;
-            camera.translation.y = player.translation.y;
+    let (player, direction) = match players.get_single() {
+        Ok((transform, player)) => (transform.translation.truncate(), player.direction),
+        Err(_) => return,
+    };
+    let facing = match direction {
+        GameDirection::Left => -1.,
+        GameDirection::Right => 1.,
+    };
+    let smoothing = |rate: f32| 1. - (-rate * time.delta_seconds()).exp();
+
+    for (mut transform, projection, mut controller) in cameras.iter_mut() {
+        let mut focus = controller.focus.unwrap_or(player);
+        focus.x = focus.x.clamp(
+            player.x - DEAD_ZONE_HALF_WIDTH,
+            player.x + DEAD_ZONE_HALF_WIDTH,
+        );
+        focus.y = focus
+            .y
+            .clamp(player.y - DEAD_ZONE_ABOVE, player.y + DEAD_ZONE_BELOW);
+        controller.focus = Some(focus);
+        controller.look_ahead += (facing * LOOK_AHEAD_DISTANCE - controller.look_ahead)
+            * smoothing(LOOK_AHEAD_SMOOTHING);
+
+        let mut target = Vec2::new(focus.x + controller.look_ahead, focus.y + VERTICAL_OFFSET);
+        if let Some(bounds) = &bounds {
+            let (half_width, half_height) = (projection.right, projection.top);
+            target.x = clamp_to_bounds(target.x, half_width, bounds.left, bounds.right);
+            target.y = clamp_to_bounds(target.y, half_height, bounds.bottom, bounds.top);
         }
+
+        let position = match controller.position {
+            Some(position) => position.lerp(target, smoothing(CAMERA_SMOOTHING)),
+            None => target,
+        };
+        controller.position = Some(position);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+// Keeps the view inside the bounds, or centers it on them if it doesn't fit
+fn clamp_to_bounds(center: f32, half_size: f32, min: f32, max: f32) -> f32 {
+    if max - min < 2. * half_size {
+        (min + max) / 2.
+    } else {
+        center.clamp(min + half_size, max - half_size)
     }
 }
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::game::CameraController;
+use crate::settings::Settings;
+use crate::AppState;
+
+const TRAUMA_DECAY: f32 = 1.5;
+const MAX_SHAKE_OFFSET: f32 = 0.6;
+const MAX_SHAKE_ANGLE: f32 = 0.03;
+const SHAKE_FREQUENCY: f32 = 25.0;
+const ZOOM_DECAY: f32 = 0.4;
+
+pub struct CameraEffectsPlugin;
+
+impl Plugin for CameraEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(trigger_camera_effects),
+        )
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            apply_camera_effects.before(TransformSystem::TransformPropagate),
+        )
+        .add_event::<CameraEffectEvent>();
+    }
+}
+
+pub enum CameraEffectEvent {
+    Hit,
+    Kill,
+    StrongShot,
+    PlayerDeath,
+}
+
+impl CameraEffectEvent {
+    // (trauma, zoom)
+    fn effects(&self) -> (f32, f32) {
+        match self {
+            CameraEffectEvent::Hit => (0.2, 0.),
+            CameraEffectEvent::Kill => (0.3, 0.03),
+            CameraEffectEvent::StrongShot => (0.15, 0.),
+            CameraEffectEvent::PlayerDeath => (0.6, 0.08),
+        }
+    }
+}
+
+// Trauma makes the camera shake with its square, so small hits barely move it. Zoom pulses
+// shrink the view, both fade out.
+#[derive(Component, Default)]
+pub struct CameraEffects {
+    trauma: f32,
+    zoom: f32,
+    time: f32,
+}
+
+fn trigger_camera_effects(
+    settings: Res<Settings>,
+    mut cameras: Query<&mut CameraEffects>,
+    mut events: EventReader<CameraEffectEvent>,
+) {
+    for event in events.iter() {
+        if !settings.screen_effects {
+            continue;
+        }
+        let (trauma, zoom) = event.effects();
+        for mut effects in cameras.iter_mut() {
+            effects.trauma = (effects.trauma + trauma).min(1.);
+            effects.zoom = effects.zoom.max(zoom);
+        }
+    }
+}
+
+// Runs after the camera is moved, the effects are put on top of the position it chose
+fn apply_camera_effects(
+    time: Res<Time>,
+    mut cameras: Query<(&mut Transform, &CameraController, &mut CameraEffects)>,
+) {
+    let delta = time.delta_seconds();
+    for (mut transform, controller, mut effects) in cameras.iter_mut() {
+        effects.time += delta;
+        effects.trauma = (effects.trauma - TRAUMA_DECAY * delta).max(0.);
+        effects.zoom = (effects.zoom - ZOOM_DECAY * delta).max(0.);
+
+        let shake = effects.trauma * effects.trauma;
+        let t = effects.time * SHAKE_FREQUENCY;
+        let offset = Vec2::new(t.sin() + (2.3 * t).sin(), (1.7 * t).cos() + (2.9 * t).sin())
+            * MAX_SHAKE_OFFSET
+            * shake
+            / 2.;
+        if let Some(position) = controller.position() {
+            transform.translation = (position + offset).extend(transform.translation.z);
+        }
+        transform.rotation = Quat::from_rotation_z(MAX_SHAKE_ANGLE * shake * (3.1 * t).sin());
+        transform.scale = Vec3::new(1. - effects.zoom, 1. - effects.zoom, 1.);
+    }
+}
@@ -4,9 +4,13 @@ use rand::{thread_rng, Rng, SeedableRng};
 use rand_pcg::Pcg64;
 use rand_seeder::Seeder;
 
+use crate::game::{Difficulty, DifficultyFactors, GAME_WIDTH};
+
 const JUMP_IMPULSE: f32 = 15.0;
 const MAX_SEED_LEN: usize = 15;
 const LEVEL_SEED_LEN_MULTIPLIER: usize = 2;
+const ECTS_SCORE: usize = 10;
+const JITTER: f32 = 0.3;
 
 #[derive(Component, Copy, Clone)]
 pub enum GameDirection {
@@ -35,7 +39,7 @@ pub struct PlayersBullet;
 #[derive(Component)]
 pub struct EnemyBullet;
 
-#[derive(Component)]
+#[derive(Component, Copy, Clone)]
 pub enum Weapon {
     WeakBullet,
     StrongBullet,
@@ -71,16 +75,107 @@ pub struct Coffee;
 #[derive(Component)]
 pub struct Rust;
 
-#[derive(Component, Default)]
-pub struct Bug;
+#[derive(Component)]
+pub struct Ects;
+
+#[derive(Component, Copy, Clone, PartialEq, Eq)]
+pub enum Hazard {
+    Spikes,
+    Lava,
+    FallingBlock,
+}
+
+impl Hazard {
+    // enemies just bump into spikes, everything else kills them too
+    pub fn hurts_enemies(&self) -> bool {
+        !matches!(self, Hazard::Spikes)
+    }
+}
+
+// Block hanging in the air until the player walks beneath it. It's a hazard only while falling.
+#[derive(Component)]
+pub struct FallingBlock;
 
-#[derive(Component, Default)]
-pub struct Valgrind;
+#[derive(Copy, Clone)]
+pub enum FirePattern {
+    Single,
+    Burst,
+    Spread,
+}
+
+// Enemies shooting at the player. Every attack is telegraphed by a wind-up before the shot.
+#[derive(Component)]
+pub struct Shooter {
+    pub aim: GameDirection,
+    pub pattern: FirePattern,
+    pub reload: f32,
+    pub cooldown: Timer,
+    pub windup: Option<Timer>,
+    pub burst_left: usize,
+    pub burst_timer: Timer,
+}
+
+impl Shooter {
+    pub fn new(cooldown: f32) -> Self {
+        Shooter {
+            aim: GameDirection::Right,
+            pattern: FirePattern::Single,
+            reload: cooldown,
+            cooldown: Timer::from_seconds(cooldown, false),
+            windup: None,
+            burst_left: 0,
+            burst_timer: Timer::from_seconds(0., false),
+        }
+    }
+}
+
+// Every enemy decides what to do on its own schedule, so that they don't act in lock-step
+#[derive(Component)]
+pub struct AiTimer {
+    pub timer: Timer,
+    pub cadence: f32,
+}
+
+impl AiTimer {
+    pub fn new(cadence: f32, rng: &mut Random) -> Self {
+        AiTimer {
+            timer: Timer::from_seconds(rng.jitter(cadence), true),
+            cadence,
+        }
+    }
+}
+
+// Enemies that ignore gravity, float along a sine wave and from time to time swoop at the player
+#[derive(Component)]
+pub struct Flyer {
+    pub origin_y: f32,
+    pub phase: f32,
+    pub swoop_target: Option<Vec2>,
+    // how long the current swoop has been going on
+    pub swoop_time: f32,
+    pub swoop_timer: Timer,
+}
+
+impl Flyer {
+    pub fn new(origin_y: f32, swoop_interval: f32) -> Self {
+        Flyer {
+            origin_y,
+            phase: 0.,
+            swoop_target: None,
+            swoop_time: 0.,
+            swoop_timer: Timer::from_seconds(swoop_interval, true),
+        }
+    }
+}
 
 #[derive(Component)]
 pub struct Enemy {
     pub speed: f32,
     pub direction: GameDirection,
+    pub health: u32,
+    pub score: usize,
+    // a chasing enemy stops at the ledge between it and the player instead of turning back
+    pub waiting_at_ledge: bool,
 }
 
 impl Default for Enemy {
@@ -88,6 +183,9 @@ impl Default for Enemy {
         Enemy {
             speed: 2.0,
             direction: GameDirection::Right,
+            health: 1,
+            score: 0,
+            waiting_at_ledge: false,
         }
     }
 }
@@ -132,6 +230,11 @@ impl Random {
         self.seed = Random::generate_random_seed();
     }
 
+    // Randomly scales the value by up to JITTER in both directions
+    pub fn jitter(&mut self, value: f32) -> f32 {
+        value * self.generator.gen_range(1. - JITTER..=1. + JITTER)
+    }
+
     pub fn make_generator_for_level(&mut self, level: usize) {
         let temp_rng: Pcg64 = Seeder::from(&self.seed).make_rng();
 
@@ -143,21 +246,55 @@ impl Random {
 
         self.generator = Seeder::from(level_seed).make_rng();
     }
+
+    // A generator of its own, which gives the same numbers for the same seed however much
+    // the systems running every frame draw from this one
+    pub fn fork(&mut self) -> Random {
+        Random {
+            generator: Pcg64::seed_from_u64(self.generator.gen()),
+            seed: self.seed.clone(),
+            can_change: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum GameMode {
+    Campaign,
+    // one level going on until the player dies, getting harder the further they get
+    Endless,
 }
 
 pub struct Level {
     pub level: usize,
-    pub difficulty: f64,
+    pub difficulty: Difficulty,
+    pub factors: DifficultyFactors,
+    pub mode: GameMode,
 }
 
 impl Level {
     pub fn new() -> Self {
         Self {
             level: 1,
-            difficulty: 1.,
+            difficulty: Difficulty::Normal,
+            factors: Difficulty::Normal.curve().at_level(1),
+            mode: GameMode::Campaign,
         }
     }
 
+    // Column of the finish line, nothing is placed at or behind it
+    pub fn finish_x(&self) -> i32 {
+        match self.mode {
+            GameMode::Campaign => GAME_WIDTH as i32,
+            GameMode::Endless => i32::MAX,
+        }
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.update_difficulty();
+    }
+
     pub fn increase_level(&mut self) {
         self.level += 1;
         self.update_difficulty();
@@ -169,7 +306,59 @@ impl Level {
     }
 
     fn update_difficulty(&mut self) {
-        self.difficulty = ((self.level - 1) / 3) as f64 + 1.;
+        self.factors = self.difficulty.curve().at_level(self.level);
+    }
+}
+
+// ECTS and score gathered during the whole run. Points collected in the current level
+// are kept aside and only banked once the level is finished. Endless runs are scored by
+// the distance reached instead.
+pub struct RunStats {
+    pub ects: usize,
+    pub score: usize,
+    pub level_ects: usize,
+    pub level_score: usize,
+    pub distance: usize,
+}
+
+impl RunStats {
+    pub fn new() -> Self {
+        Self {
+            ects: 0,
+            score: 0,
+            level_ects: 0,
+            level_score: 0,
+            distance: 0,
+        }
+    }
+
+    pub fn collect_ects(&mut self) {
+        self.level_ects += 1;
+        self.level_score += ECTS_SCORE;
+    }
+
+    pub fn add_score(&mut self, score: usize) {
+        self.level_score += score;
+    }
+
+    pub fn bank_level(&mut self) {
+        self.ects += self.level_ects;
+        self.score += self.level_score;
+        self.reset_level();
+    }
+
+    pub fn reset_level(&mut self) {
+        self.level_ects = 0;
+        self.level_score = 0;
+        self.distance = 0;
+    }
+
+    pub fn reach(&mut self, distance: usize) {
+        self.distance = self.distance.max(distance);
+    }
+
+    pub fn reset(&mut self) {
+        *self = RunStats::new();
     }
 }
 
@@ -0,0 +1,224 @@
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    ExamSession,
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::ExamSession => "Exam session",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::ExamSession,
+            Difficulty::ExamSession => Difficulty::Easy,
+        }
+    }
+
+    pub fn curve(&self) -> DifficultyCurve {
+        match self {
+            Difficulty::Easy => EASY_CURVE,
+            Difficulty::Normal => NORMAL_CURVE,
+            Difficulty::Hard => HARD_CURVE,
+            Difficulty::ExamSession => EXAM_SESSION_CURVE,
+        }
+    }
+}
+
+// Factor equal to `base` on the first tier, changing by `growth` with every next tier
+#[derive(Copy, Clone)]
+pub struct Curve {
+    pub base: f64,
+    pub growth: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Curve {
+    pub fn at(&self, tier: usize) -> f64 {
+        (self.base + self.growth * tier as f64).clamp(self.min, self.max)
+    }
+}
+
+// The game gets harder every `levels_per_tier` levels. Every factor is a multiplier
+// of the values used by the game, 1 meaning no change.
+#[derive(Copy, Clone)]
+pub struct DifficultyCurve {
+    pub levels_per_tier: usize,
+    pub enemy_density: Curve,
+    pub enemy_speed: Curve,
+    pub shot_frequency: Curve,
+    pub terrain_roughness: Curve,
+    pub powerup_frequency: Curve,
+}
+
+// Factors computed from the curve for a particular level
+#[derive(Copy, Clone)]
+pub struct DifficultyFactors {
+    pub tier: usize,
+    pub enemy_density: f64,
+    pub enemy_speed: f64,
+    pub shot_frequency: f64,
+    pub terrain_roughness: f64,
+    pub powerup_frequency: f64,
+}
+
+impl DifficultyCurve {
+    pub fn at_level(&self, level: usize) -> DifficultyFactors {
+        let tier = (level - 1) / self.levels_per_tier;
+        DifficultyFactors {
+            tier,
+            enemy_density: self.enemy_density.at(tier),
+            enemy_speed: self.enemy_speed.at(tier),
+            shot_frequency: self.shot_frequency.at(tier),
+            terrain_roughness: self.terrain_roughness.at(tier),
+            powerup_frequency: self.powerup_frequency.at(tier),
+        }
+    }
+}
+
+const EASY_CURVE: DifficultyCurve = DifficultyCurve {
+    levels_per_tier: 4,
+    enemy_density: Curve {
+        base: 0.6,
+        growth: 0.4,
+        min: 0.,
+        max: 3.,
+    },
+    enemy_speed: Curve {
+        base: 0.8,
+        growth: 0.05,
+        min: 0.,
+        max: 1.2,
+    },
+    shot_frequency: Curve {
+        base: 0.7,
+        growth: 0.1,
+        min: 0.,
+        max: 1.2,
+    },
+    terrain_roughness: Curve {
+        base: 0.7,
+        growth: 0.1,
+        min: 0.,
+        max: 1.2,
+    },
+    powerup_frequency: Curve {
+        base: 1.5,
+        growth: -0.15,
+        min: 0.6,
+        max: 1.5,
+    },
+};
+
+const NORMAL_CURVE: DifficultyCurve = DifficultyCurve {
+    levels_per_tier: 3,
+    enemy_density: Curve {
+        base: 1.,
+        growth: 1.,
+        min: 0.,
+        max: 5.,
+    },
+    enemy_speed: Curve {
+        base: 1.,
+        growth: 0.1,
+        min: 0.,
+        max: 1.5,
+    },
+    shot_frequency: Curve {
+        base: 1.,
+        growth: 0.2,
+        min: 0.,
+        max: 2.,
+    },
+    terrain_roughness: Curve {
+        base: 1.,
+        growth: 0.15,
+        min: 0.,
+        max: 1.75,
+    },
+    powerup_frequency: Curve {
+        base: 1.,
+        growth: -0.25,
+        min: 0.25,
+        max: 1.,
+    },
+};
+
+const HARD_CURVE: DifficultyCurve = DifficultyCurve {
+    levels_per_tier: 3,
+    enemy_density: Curve {
+        base: 1.5,
+        growth: 1.,
+        min: 0.,
+        max: 6.,
+    },
+    enemy_speed: Curve {
+        base: 1.2,
+        growth: 0.1,
+        min: 0.,
+        max: 1.75,
+    },
+    shot_frequency: Curve {
+        base: 1.3,
+        growth: 0.25,
+        min: 0.,
+        max: 2.5,
+    },
+    terrain_roughness: Curve {
+        base: 1.25,
+        growth: 0.15,
+        min: 0.,
+        max: 2.,
+    },
+    powerup_frequency: Curve {
+        base: 0.75,
+        growth: -0.15,
+        min: 0.2,
+        max: 0.75,
+    },
+};
+
+const EXAM_SESSION_CURVE: DifficultyCurve = DifficultyCurve {
+    levels_per_tier: 2,
+    enemy_density: Curve {
+        base: 2.,
+        growth: 1.,
+        min: 0.,
+        max: 8.,
+    },
+    enemy_speed: Curve {
+        base: 1.4,
+        growth: 0.15,
+        min: 0.,
+        max: 2.,
+    },
+    shot_frequency: Curve {
+        base: 1.6,
+        growth: 0.3,
+        min: 0.,
+        max: 3.,
+    },
+    terrain_roughness: Curve {
+        base: 1.5,
+        growth: 0.2,
+        min: 0.,
+        max: 2.,
+    },
+    powerup_frequency: Curve {
+        base: 0.5,
+        growth: -0.1,
+        min: 0.15,
+        max: 0.5,
+    },
+};
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+use crate::game::{
+    Ects, ParticleEffect, ParticleEvent, PlaySfx, Player, RunStats, SAFE_ZONE_WIDTH,
+};
+use crate::{AppState, GameTextures, Random};
+
+use super::utils::*;
+
+pub struct EctsPlugin;
+
+pub struct EctsEvent {
+    ects: Entity,
+}
+
+const ARC_PROBABILITY: f64 = 0.4;
+const PLATFORM_ECTS_PROBABILITY: f64 = 0.1;
+const HIDDEN_ECTS_PROBABILITY: f64 = 0.03;
+const ARC_LOW_HEIGHT: f32 = 1.25;
+const ARC_HIGH_HEIGHT: f32 = 2.0;
+const PLATFORM_ECTS_HEIGHT: f32 = 1.0;
+const HIDDEN_ECTS_HEIGHT: f32 = 6.0;
+
+impl Plugin for EctsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RunStats::new())
+            .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(reset_level_stats))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(collect_ects)
+                    .with_system(handle_ects_event),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::WinMenu).with_system(bank_level_stats))
+            .add_event::<EctsEvent>();
+    }
+}
+
+pub fn spawn_ects(commands: &mut Commands, game_textures: &Res<GameTextures>, x: f32, y: f32) {
+    let mut ects_entity = spawn_static_object(
+        commands,
+        create_sprite_bundle(game_textures.ects.clone(), (0.6, 0.6), (x, y, 10.0)),
+    );
+    ects_entity =
+        spawn_sensor_collider(commands, ects_entity, Collider::round_cuboid(0.2, 0.2, 0.1));
+    commands.entity(ects_entity).insert(Ects);
+}
+
+fn collect_ects(
+    players: Query<Entity, With<Player>>,
+    ects: Query<Entity, With<Ects>>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut send_event: EventWriter<EctsEvent>,
+) {
+    for collision_event in collision_events.iter() {
+        if let CollisionEvent::Started(ent1, ent2, _) = collision_event {
+            match (
+                players.get(*ent1),
+                ects.get(*ent2),
+                players.get(*ent2),
+                ects.get(*ent1),
+            ) {
+                (Ok(_), Ok(ects), _, _) | (_, _, Ok(_), Ok(ects)) => {
+                    send_event.send(EctsEvent { ects });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn handle_ects_event(
+    mut commands: Commands,
+    mut ects_events: EventReader<EctsEvent>,
+    mut stats: ResMut<RunStats>,
+    transforms: Query<&Transform, With<Ects>>,
+    mut send_sfx: EventWriter<PlaySfx>,
+    mut send_particle_event: EventWriter<ParticleEvent>,
+) {
+    ects_events.iter().for_each(|ects_event| {
+        stats.collect_ects();
+        if let Ok(transform) = transforms.get(ects_event.ects) {
+            send_sfx.send(PlaySfx::at("ects", transform.translation));
+            send_particle_event.send(ParticleEvent::new(
+                ParticleEffect::PickupSparkle,
+                transform.translation,
+            ));
+        }
+        commands.entity(ects_event.ects).despawn_recursive();
+    });
+}
+
+// ECTS gathered in a level are lost when the level is failed or restarted
+fn reset_level_stats(mut stats: ResMut<RunStats>) {
+    stats.reset_level();
+}
+
+fn bank_level_stats(mut stats: ResMut<RunStats>) {
+    stats.bank_level();
+}
+
+pub fn add_ects(
+    commands: &mut Commands,
+    world: &[(i32, usize)],
+    game_textures: &Res<GameTextures>,
+    rng: &mut Random,
+) {
+    world.windows(3).for_each(|window| {
+        let (prev_height, (x, height), next_height) = (window[0].1, window[1], window[2].1);
+        if x <= SAFE_ZONE_WIDTH as i32 {
+            return;
+        }
+
+        if height != next_height && rng.generator.gen_bool(ARC_PROBABILITY) {
+            add_arc(commands, game_textures, x, height.max(next_height));
+        } else if prev_height == height
+            && height == next_height
+            && rng.generator.gen_bool(PLATFORM_ECTS_PROBABILITY)
+        {
+            spawn_ects(
+                commands,
+                game_textures,
+                x as f32,
+                height as f32 + PLATFORM_ECTS_HEIGHT,
+            );
+        }
+
+        if rng.generator.gen_bool(HIDDEN_ECTS_PROBABILITY) {
+            spawn_ects(
+                commands,
+                game_textures,
+                x as f32,
+                height as f32 + HIDDEN_ECTS_HEIGHT,
+            );
+        }
+    });
+}
+
+// Arc of three ECTS over the edge between column x and x + 1
+fn add_arc(commands: &mut Commands, game_textures: &Res<GameTextures>, x: i32, height: usize) {
+    let (x, height) = (x as f32, height as f32);
+    spawn_ects(commands, game_textures, x - 0.5, height + ARC_LOW_HEIGHT);
+    spawn_ects(commands, game_textures, x + 0.5, height + ARC_HIGH_HEIGHT);
+    spawn_ects(commands, game_textures, x + 1.5, height + ARC_LOW_HEIGHT);
+}
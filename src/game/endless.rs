@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use crate::game::map::{add_chunk_colliders, get_next_height, populate, spawn_wall};
+use crate::game::platforms::carve_gaps;
+use crate::game::{
+    despawn_block_row, Block, BlockRow, Dying, Ects, Enemy, EnemyArchetypes, EnemyAssets,
+    EnemySpawn, GameMode, Hazard, LevelBounds, LevelProgression, Platform, Player, Powerup,
+    ProgressionAssets, RowCollider, RunStats, Terrain, Wall, CHUNK_WIDTH, GAME_WIDTH, WALL_HEIGHT,
+};
+use crate::{AppState, GameTextures, Level, Random};
+
+// Chunks are generated this far ahead of the player and kept this far behind them
+const CHUNKS_AHEAD: i32 = 3;
+const CHUNKS_BEHIND: i32 = 2;
+// The run gets one level harder every time the player gets this much further
+const LEVEL_LENGTH: usize = GAME_WIDTH;
+// A player who loses a life comes back this many columns behind where they died
+const RESPAWN_DISTANCE: i32 = 2;
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(generate_chunks_ahead)
+                .with_system(remove_chunks_behind)
+                .with_system(track_distance),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(end_endless_run));
+    }
+}
+
+// Level of an endless run, which only exists while one is being played
+pub struct EndlessRun {
+    terrain: Terrain,
+    material: Handle<ColorMaterial>,
+    chunk_meshes: BTreeMap<i32, Entity>,
+    // first column that isn't generated yet and the height of the one before it
+    next_x: i32,
+    height: usize,
+    // chunks before this one were passed and are gone
+    first_chunk: i32,
+    biome_enemies: Vec<EnemySpawn>,
+    // forked from the seed when the run starts, the shared generator is also used by the AI
+    // every frame, which would make the same seed give different runs
+    rng: Random,
+}
+
+impl EndlessRun {
+    // The terrain generated so far has to end at a chunk boundary
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        terrain: Terrain,
+        material: Handle<ColorMaterial>,
+        next_x: i32,
+        height: usize,
+        biome_enemies: Vec<EnemySpawn>,
+        rng: Random,
+    ) -> Self {
+        let chunk_meshes = terrain
+            .chunks()
+            .map(|chunk| {
+                (
+                    chunk,
+                    terrain.spawn_chunk(commands, meshes, &material, chunk),
+                )
+            })
+            .collect();
+        let first_chunk = terrain.chunks().next().unwrap_or(0);
+        EndlessRun {
+            terrain,
+            material,
+            chunk_meshes,
+            next_x,
+            height,
+            first_chunk,
+            biome_enemies,
+            rng,
+        }
+    }
+
+    // Column a player who lost a life comes back on, the closest one behind where they died
+    // that has ground and passes the check. The wall behind the player is never passed.
+    pub fn respawn_column(
+        &self,
+        died_at: f32,
+        left: f32,
+        is_safe: impl Fn(i32) -> bool,
+    ) -> Option<(i32, i32)> {
+        let from = died_at.round() as i32 - RESPAWN_DISTANCE;
+        let to = left.ceil() as i32 + 1;
+        (to..=from).rev().find_map(|x| {
+            let height = self.terrain.height(x)?;
+            is_safe(x).then_some((x, height))
+        })
+    }
+
+    // The edge tiles depend on the neighbouring columns, so a chunk is built again once
+    // the chunk next to it appears
+    fn rebuild_chunk(&mut self, commands: &mut Commands, meshes: &mut Assets<Mesh>, chunk: i32) {
+        if let Some(mesh) = self.chunk_meshes.remove(&chunk) {
+            commands.entity(mesh).despawn();
+        }
+        if chunk >= self.first_chunk {
+            let mesh = self
+                .terrain
+                .spawn_chunk(commands, meshes, &self.material, chunk);
+            self.chunk_meshes.insert(chunk, mesh);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_chunks_ahead(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    level: Res<Level>,
+    enemy_assets: Res<EnemyAssets>,
+    archetypes: Res<Assets<EnemyArchetypes>>,
+    progression_assets: Res<ProgressionAssets>,
+    progressions: Res<Assets<LevelProgression>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    run: Option<ResMut<EndlessRun>>,
+    bounds: Option<ResMut<LevelBounds>>,
+    players: Query<&Transform, With<Player>>,
+) {
+    let (mut run, mut bounds, player) = match (run, bounds, players.get_single()) {
+        (Some(run), Some(bounds), Ok(transform)) => (run, bounds, transform.translation.x),
+        _ => return,
+    };
+    let run = &mut *run;
+
+    while (run.next_x as f32) < player + (CHUNKS_AHEAD * CHUNK_WIDTH) as f32 {
+        let first = run.next_x;
+        let mut world = Vec::with_capacity(CHUNK_WIDTH as usize);
+        for x in first..first + CHUNK_WIDTH {
+            run.height = get_next_height(&mut run.rng, run.height, level.factors.terrain_roughness);
+            world.push((x, run.height));
+        }
+        let gaps = carve_gaps(&mut world, &mut run.rng, &level);
+        add_chunk_colliders(&world, &mut commands);
+
+        // the enemies follow the level band the run has got to
+        let band = progressions
+            .get(&progression_assets.progression)
+            .and_then(|progression| progression.band(level.level));
+        let enemies = archetypes
+            .get(&enemy_assets.archetypes)
+            .map(|archetypes| (&*enemy_assets, archetypes));
+        let band_enemies = band.map_or(&[][..], |band| &band.enemies);
+        populate(
+            &mut commands,
+            &world,
+            &gaps,
+            &game_textures,
+            enemies,
+            &[band_enemies, &run.biome_enemies],
+            &mut run.rng,
+            &level,
+        );
+
+        for &(x, height) in world.iter() {
+            run.terrain.set_column(x, 0, height as i32);
+            bounds.top = bounds.top.max(height as f32 + WALL_HEIGHT);
+        }
+        run.next_x += CHUNK_WIDTH;
+
+        let chunk = first.div_euclid(CHUNK_WIDTH);
+        run.rebuild_chunk(&mut commands, &mut meshes, chunk - 1);
+        run.rebuild_chunk(&mut commands, &mut meshes, chunk);
+    }
+}
+
+// Everything far enough behind the player is removed, and a wall keeps them from walking
+// back into the void
+#[allow(clippy::type_complexity)]
+fn remove_chunks_behind(
+    mut commands: Commands,
+    run: Option<ResMut<EndlessRun>>,
+    bounds: Option<ResMut<LevelBounds>>,
+    players: Query<&Transform, With<Player>>,
+    passed: Query<
+        (Entity, &Transform),
+        (
+            Or<(
+                With<Wall>,
+                With<Enemy>,
+                With<Dying>,
+                With<Powerup>,
+                With<Ects>,
+                With<Hazard>,
+                With<Platform>,
+            )>,
+            Without<Block>,
+            Without<RowCollider>,
+        ),
+    >,
+    block_rows: Query<(Entity, &BlockRow)>,
+) {
+    let (mut run, mut bounds, player) = match (run, bounds, players.get_single()) {
+        (Some(run), Some(bounds), Ok(transform)) => (run, bounds, transform.translation.x),
+        _ => return,
+    };
+
+    let first_chunk = (player as i32).div_euclid(CHUNK_WIDTH) - CHUNKS_BEHIND;
+    if first_chunk <= run.first_chunk {
+        return;
+    }
+    let kept = run.chunk_meshes.split_off(&first_chunk);
+    for mesh in std::mem::replace(&mut run.chunk_meshes, kept).into_values() {
+        commands.entity(mesh).despawn();
+    }
+    run.first_chunk = first_chunk;
+
+    let first_x = first_chunk * CHUNK_WIDTH;
+    let edge = first_x as f32 - 0.5;
+    for (entity, transform) in passed.iter() {
+        if transform.translation.x < edge {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for (row, block_row) in block_rows.iter() {
+        if block_row
+            .blocks
+            .keys()
+            .next_back()
+            .is_none_or(|&x| x < first_x)
+        {
+            despawn_block_row(&mut commands, row, block_row);
+        }
+    }
+
+    run.terrain.remove_before(first_x);
+    let height = run.terrain.height(first_x).unwrap_or(0);
+    spawn_wall(&mut commands, first_x - 1, height as f32 + WALL_HEIGHT);
+    bounds.left = edge;
+}
+
+// The run gets harder with the distance, which is also its score
+fn track_distance(
+    mut level: ResMut<Level>,
+    mut stats: ResMut<RunStats>,
+    players: Query<&Transform, With<Player>>,
+) {
+    if level.mode != GameMode::Endless {
+        return;
+    }
+    if let Ok(transform) = players.get_single() {
+        stats.reach(transform.translation.x.max(0.) as usize);
+        while level.level <= stats.distance / LEVEL_LENGTH {
+            level.increase_level();
+        }
+    }
+}
+
+fn end_endless_run(mut commands: Commands) {
+    commands.remove_resource::<EndlessRun>();
+}
@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+use crate::game::{DeadPlayerEvent, Enemy, FallingBlock, Hazard, Player, Wall, SAFE_ZONE_WIDTH};
+use crate::{AppState, GameTextures, Level, Random};
+
+use super::utils::*;
+
+const HAZARD_PROBABILITY: f64 = 0.1;
+const HAZARD_MARGIN: i32 = 5;
+const MIN_HAZARD_DISTANCE: i32 = 4;
+const SPIKES_HEIGHT: f32 = 0.4;
+const LAVA_HEIGHT: f32 = 0.25;
+const FALLING_BLOCK_HEIGHT: f32 = 4.0;
+const FALLING_BLOCK_TRIGGER_WIDTH: f32 = 0.75;
+// blocks only fall on a player this close below them, with nothing solid in between
+const FALLING_BLOCK_TRIGGER_DEPTH: f32 = 2. * FALLING_BLOCK_HEIGHT;
+const FALLING_BLOCK_GRAVITY_SCALE: f32 = 1.0;
+const FALL_DEATH_HEIGHT: f32 = -5.0;
+
+pub struct HazardsPlugin;
+
+impl Plugin for HazardsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(hazard_contact)
+                .with_system(drop_falling_blocks)
+                .with_system(falling_block_lands)
+                .with_system(fall_out_of_map),
+        );
+    }
+}
+
+fn spawn_spikes(commands: &mut Commands, game_textures: &Res<GameTextures>, x: f32, y: f32) {
+    spawn_static_object(
+        commands,
+        create_sprite_bundle(
+            game_textures.spikes.clone(),
+            (1.0, SPIKES_HEIGHT),
+            (x, y + SPIKES_HEIGHT / 2., 1.0),
+        ),
+    );
+    spawn_static_collider_object(
+        commands,
+        (x - 0.4, y),
+        (x + 0.4, y + SPIKES_HEIGHT / 2.),
+        Hazard::Spikes,
+    );
+}
+
+fn spawn_lava(commands: &mut Commands, game_textures: &Res<GameTextures>, x: f32, y: f32) {
+    spawn_static_object(
+        commands,
+        create_sprite_bundle(
+            game_textures.lava.clone(),
+            (1.0, LAVA_HEIGHT),
+            (x, y + LAVA_HEIGHT / 2., 1.0),
+        ),
+    );
+    spawn_static_collider_object(
+        commands,
+        (x - 0.45, y),
+        (x + 0.45, y + LAVA_HEIGHT),
+        Hazard::Lava,
+    );
+}
+
+fn spawn_falling_block(commands: &mut Commands, game_textures: &Res<GameTextures>, x: f32, y: f32) {
+    let mut block_entity = spawn_static_object(
+        commands,
+        create_sprite_bundle(game_textures.falling_block.clone(), (1.0, 1.0), (x, y, 1.0)),
+    );
+    block_entity = spawn_solid_collider(commands, block_entity, Collider::cuboid(0.5, 0.5), None);
+    commands.entity(block_entity).insert(FallingBlock);
+}
+
+// Hazards are put in the middle of flat pieces of the map, so that there's always a place
+// to jump from and to land on
+pub fn add_hazards(
+    commands: &mut Commands,
+    world: &[(i32, usize)],
+    game_textures: &Res<GameTextures>,
+    rng: &mut Random,
+    level: &Res<Level>,
+) {
+    let probability = (HAZARD_PROBABILITY * level.factors.terrain_roughness).min(1.);
+    let mut last_hazard = i32::MIN;
+
+    world.windows(3).for_each(|window| {
+        let ((left_x, left), (x, height), (right_x, right)) = (window[0], window[1], window[2]);
+        if right_x - left_x != 2
+            || left != height
+            || right != height
+            || x <= SAFE_ZONE_WIDTH as i32 + HAZARD_MARGIN
+            || x >= level.finish_x() - HAZARD_MARGIN
+            || x - last_hazard < MIN_HAZARD_DISTANCE
+            || !rng.generator.gen_bool(probability)
+        {
+            return;
+        }
+
+        last_hazard = x;
+        let (x, surface) = (x as f32, height as f32 + 0.5);
+        match rng.generator.gen_range(0..3) {
+            0 => spawn_spikes(commands, game_textures, x, surface),
+            1 => spawn_lava(commands, game_textures, x, surface),
+            _ => spawn_falling_block(commands, game_textures, x, surface + FALLING_BLOCK_HEIGHT),
+        }
+    });
+}
+
+fn hazard_contact(
+    mut commands: Commands,
+    players: Query<Entity, With<Player>>,
+    enemies: Query<Entity, With<Enemy>>,
+    hazards: Query<&Hazard>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut send_dead_player_event: EventWriter<DeadPlayerEvent>,
+) {
+    for collision_event in collision_events.iter() {
+        if let CollisionEvent::Started(ent1, ent2, _) = collision_event {
+            match (
+                players.get(*ent1),
+                hazards.get(*ent2),
+                players.get(*ent2),
+                hazards.get(*ent1),
+            ) {
+                (Ok(_), Ok(_), _, _) | (_, _, Ok(_), Ok(_)) => {
+                    send_dead_player_event.send(DeadPlayerEvent)
+                }
+                _ => {}
+            }
+
+            match (
+                enemies.get(*ent1),
+                hazards.get(*ent2),
+                enemies.get(*ent2),
+                hazards.get(*ent1),
+            ) {
+                (Ok(enemy), Ok(hazard), _, _) | (_, _, Ok(enemy), Ok(hazard))
+                    if hazard.hurts_enemies() =>
+                {
+                    commands.entity(enemy).despawn_recursive();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn drop_falling_blocks(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    players: Query<(Entity, &Transform), With<Player>>,
+    blocks: Query<(Entity, &Transform), (With<FallingBlock>, Without<Hazard>)>,
+    walls: Query<Entity, With<Wall>>,
+) {
+    if let Ok((player, player_transform)) = players.get_single() {
+        for (block, transform) in blocks.iter() {
+            if (player_transform.translation.x - transform.translation.x).abs()
+                >= FALLING_BLOCK_TRIGGER_WIDTH
+                || player_transform.translation.y >= transform.translation.y
+            {
+                continue;
+            }
+            // the player has to be the first thing below the block, looking down right above them
+            let hit = rapier_context.cast_ray(
+                Vec2::new(player_transform.translation.x, transform.translation.y),
+                Vec2::new(0., -1.),
+                FALLING_BLOCK_TRIGGER_DEPTH,
+                true,
+                InteractionGroups::all(),
+                Some(&|entity| entity != block && (entity == player || walls.get(entity).is_ok())),
+            );
+            if hit.is_some_and(|(entity, _)| entity == player) {
+                commands
+                    .entity(block)
+                    .insert(RigidBody::Dynamic)
+                    .insert(LockedAxes::ROTATION_LOCKED)
+                    .insert(GravityScale(FALLING_BLOCK_GRAVITY_SCALE))
+                    .insert(Hazard::FallingBlock);
+            }
+        }
+    }
+}
+
+// Once on the ground the block stays there as a part of the map
+fn falling_block_lands(
+    mut commands: Commands,
+    blocks: Query<Entity, (With<FallingBlock>, With<Hazard>)>,
+    walls: Query<Entity, With<Wall>>,
+    mut collision_events: EventReader<CollisionEvent>,
+) {
+    for collision_event in collision_events.iter() {
+        if let CollisionEvent::Started(ent1, ent2, _) = collision_event {
+            match (
+                blocks.get(*ent1),
+                walls.get(*ent2),
+                blocks.get(*ent2),
+                walls.get(*ent1),
+            ) {
+                (Ok(block), Ok(_), _, _) | (_, _, Ok(block), Ok(_)) => {
+                    commands
+                        .entity(block)
+                        .remove::<FallingBlock>()
+                        .remove::<Hazard>()
+                        .insert(RigidBody::Fixed)
+                        .insert(Wall);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Falling into a gap kills everyone
+fn fall_out_of_map(
+    mut commands: Commands,
+    players: Query<&Transform, With<Player>>,
+    enemies: Query<(Entity, &Transform), With<Enemy>>,
+    mut send_dead_player_event: EventWriter<DeadPlayerEvent>,
+) {
+    if let Ok(player) = players.get_single() {
+        if player.translation.y < FALL_DEATH_HEIGHT {
+            send_dead_player_event.send(DeadPlayerEvent);
+        }
+    }
+
+    for (enemy, transform) in enemies.iter() {
+        if transform.translation.y < FALL_DEATH_HEIGHT {
+            commands.entity(enemy).despawn_recursive();
+        }
+    }
+}
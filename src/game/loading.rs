@@ -0,0 +1,40 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+
+use crate::game::{BiomeAssets, EnemyAssets, ProgressionAssets};
+use crate::AppState;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(AppState::Loading).with_system(wait_for_data));
+    }
+}
+
+// Levels are generated from the enemies, progression and biomes data, so the menus only show
+// up once all of it is loaded
+fn wait_for_data(
+    mut state: ResMut<State<AppState>>,
+    asset_server: Res<AssetServer>,
+    enemy_assets: Res<EnemyAssets>,
+    progression_assets: Res<ProgressionAssets>,
+    biome_assets: Res<BiomeAssets>,
+    mut reported: Local<bool>,
+) {
+    let handles = [
+        enemy_assets.archetypes.clone_untyped(),
+        progression_assets.progression.clone_untyped(),
+        biome_assets.biomes.clone_untyped(),
+    ];
+    match asset_server.get_group_load_state(handles.iter().map(|handle| handle.id)) {
+        LoadState::Loaded => state
+            .set(AppState::MainMenu)
+            .expect("Couldn't switch state to MainMenu"),
+        LoadState::Failed if !*reported => {
+            error!("Game data couldn't be loaded, check the files in assets/data");
+            *reported = true;
+        }
+        _ => {}
+    }
+}
@@ -1,9 +1,19 @@
 use bevy::prelude::*;
 use rand::prelude::*;
 
+use crate::game::blocks::add_blocks;
+use crate::game::ects::add_ects;
+use crate::game::hazards::add_hazards;
 use crate::game::monster::add_enemies;
+use crate::game::parallax::add_parallax_layers;
+use crate::game::platforms::{add_gap_platforms, add_level_platforms, carve_gaps, Gap};
 use crate::game::powerups::add_powerups;
-use crate::game::{FinishLine, Level, Wall};
+use crate::game::progression::add_encounters;
+use crate::game::{
+    BiomeAssets, Biomes, CurrentBiome, EndlessRun, EnemyArchetypes, EnemyAssets, EnemySpawn,
+    FinishLine, GameMode, Level, LevelBounds, LevelProgression, ProgressionAssets, Terrain, Wall,
+    CHUNK_WIDTH,
+};
 use crate::{AppState, GameTextures, Random};
 
 use super::utils::*;
@@ -11,10 +21,10 @@ use super::utils::*;
 const BEGIN_WIDTH: usize = 10;
 pub const SAFE_ZONE_WIDTH: usize = 5;
 pub const GAME_WIDTH: usize = 150;
-const MAP_WIDTH: usize = GAME_WIDTH + BEGIN_WIDTH;
-const WALL_HEIGHT: f32 = 20.0;
+pub const WALL_HEIGHT: f32 = 20.0;
 const TILE_SIZE: f32 = 1.0;
 const HALF_TILE_SIZE: f32 = TILE_SIZE / 2.0;
+const HEIGHT_CHANGE_CHANCE: f64 = 29.0;
 
 pub struct MapPlugin;
 
@@ -24,21 +34,146 @@ impl Plugin for MapPlugin {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn generate_map(
     mut rng: ResMut<Random>,
     mut commands: Commands,
     game_textures: Res<GameTextures>,
     level: Res<Level>,
+    enemy_assets: Res<EnemyAssets>,
+    archetypes: Res<Assets<EnemyArchetypes>>,
+    progression_assets: Res<ProgressionAssets>,
+    progressions: Res<Assets<LevelProgression>>,
+    biome_assets: Res<BiomeAssets>,
+    biomes: Res<Assets<Biomes>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    let world = create_world(&mut rng);
-    add_floor(&mut commands, &game_textures, &world);
-    add_start_and_finish_line(&mut commands, &game_textures, &world);
-    add_enemies(&mut commands, &world, &game_textures, &mut rng, &level);
-    add_powerups(&mut commands, &world, game_textures, &mut rng, &level);
+    let band = progressions
+        .get(&progression_assets.progression)
+        .and_then(|progression| progression.band(level.level));
+    let biome = biomes
+        .get(&biome_assets.biomes)
+        .and_then(|biomes| biomes.choose(band, &mut rng));
+    let current_biome = match biome {
+        Some(biome) => {
+            add_parallax_layers(&mut commands, biome, &biome_assets);
+            CurrentBiome::new(biome, &biome_assets, &game_textures)
+        }
+        None => CurrentBiome::fallback(&game_textures),
+    };
+
+    // endless runs start with a single chunk, the rest is streamed in as the player goes
+    let width = match level.mode {
+        GameMode::Campaign => GAME_WIDTH,
+        GameMode::Endless => CHUNK_WIDTH as usize,
+    };
+    let mut world = create_world(&mut rng, level.factors.terrain_roughness, width);
+    let gaps = carve_gaps(&mut world, &mut rng, &level);
+    let mut terrain = Terrain::from_world(&world);
+    add_start_wall(&mut commands, &mut terrain);
+    let material = materials.add(ColorMaterial::from(current_biome.tileset.clone()));
+
+    let enemies = archetypes
+        .get(&enemy_assets.archetypes)
+        .map(|archetypes| (&*enemy_assets, archetypes));
+    // the data is loaded before the game can be started, but it may have no band for the level
+    if band.is_none() {
+        warn!(
+            "No level band covers level {}, it will have no enemies",
+            level.level
+        );
+    }
+    let band_enemies = band.map_or(&[][..], |band| &band.enemies);
+    let biome_enemies = biome.map_or(&[][..], |biome| &biome.enemies);
+    populate(
+        &mut commands,
+        &world,
+        &gaps,
+        &game_textures,
+        enemies,
+        &[band_enemies, biome_enemies],
+        &mut rng,
+        &level,
+    );
+
+    let right = match level.mode {
+        GameMode::Campaign => {
+            if let Some(band) = band {
+                add_level_platforms(&mut commands, &world, &band.platforms, &game_textures);
+                if let Some((enemy_assets, archetypes)) = enemies {
+                    add_encounters(
+                        &mut commands,
+                        &world,
+                        enemy_assets,
+                        archetypes,
+                        band,
+                        &mut rng,
+                        &level,
+                    );
+                }
+            }
+            let right = add_finish_line(&mut commands, &game_textures, &world, &mut terrain);
+            terrain.spawn_chunks(&mut commands, &mut meshes, &material);
+            add_colliders(&world, -HALF_TILE_SIZE, &mut commands);
+            right
+        }
+        GameMode::Endless => {
+            add_chunk_colliders(&world, &mut commands);
+            let height = world.last().map_or(0, |&(_, height)| height);
+            let run = EndlessRun::new(
+                &mut commands,
+                &mut meshes,
+                terrain,
+                material,
+                width as i32,
+                height,
+                biome_enemies.to_vec(),
+                rng.fork(),
+            );
+            commands.insert_resource(run);
+            f32::INFINITY
+        }
+    };
+
+    commands.insert_resource(level_bounds(&world, right));
+    commands.insert_resource(current_biome);
+}
+
+// Fills a stretch of the level with everything standing on its terrain
+#[allow(clippy::too_many_arguments)]
+pub fn populate(
+    commands: &mut Commands,
+    world: &[(i32, usize)],
+    gaps: &[Gap],
+    game_textures: &Res<GameTextures>,
+    enemies: Option<(&EnemyAssets, &EnemyArchetypes)>,
+    spawns: &[&[EnemySpawn]],
+    rng: &mut Random,
+    level: &Res<Level>,
+) {
+    add_gap_platforms(commands, gaps, game_textures, rng);
+    if let Some((enemy_assets, archetypes)) = enemies {
+        for spawns in spawns {
+            add_enemies(
+                commands,
+                world,
+                enemy_assets,
+                archetypes,
+                spawns,
+                rng,
+                level,
+            );
+        }
+    }
+    add_blocks(commands, world, game_textures, rng, level);
+    add_hazards(commands, world, game_textures, rng, level);
+    add_ects(commands, world, game_textures, rng);
+    add_powerups(commands, world, game_textures, rng, level);
 }
 
-fn create_world(rng: &mut ResMut<Random>) -> Vec<(i32, usize)> {
-    let mut heights: Vec<(i32, usize)> = Vec::with_capacity(MAP_WIDTH);
+fn create_world(rng: &mut Random, roughness: f64, width: usize) -> Vec<(i32, usize)> {
+    let mut heights: Vec<(i32, usize)> = Vec::with_capacity(BEGIN_WIDTH + width);
     let mut height: usize = 0;
 
     // we want to start with a safe zone and some space on the left side of the player
@@ -47,16 +182,16 @@ fn create_world(rng: &mut ResMut<Random>) -> Vec<(i32, usize)> {
         heights.push((i, height));
     });
 
-    (SAFE_ZONE_WIDTH..GAME_WIDTH).for_each(|i| {
+    (SAFE_ZONE_WIDTH..width).for_each(|i| {
         heights.push((i as i32, height));
-        height = get_next_height(rng, height)
+        height = get_next_height(rng, height, roughness)
     });
 
     heights
 }
 
-fn get_next_height(rng: &mut ResMut<Random>, current_height: usize) -> usize {
-    let next_height = current_height as i32 + get_random_height_delta(rng);
+pub fn get_next_height(rng: &mut Random, current_height: usize, roughness: f64) -> usize {
+    let next_height = current_height as i32 + get_random_height_delta(rng, roughness);
 
     if next_height >= 0 {
         next_height as usize
@@ -65,30 +200,20 @@ fn get_next_height(rng: &mut ResMut<Random>, current_height: usize) -> usize {
     }
 }
 
-fn get_random_height_delta(rng: &mut ResMut<Random>) -> i32 {
-    match rng.generator.gen_range(0..100) {
-        0..=70 => 0,
-        71..=80 => -1,
-        81..=90 => 1,
-        _ => 2,
+// Roughness scales the chance of a height change, with roughness 1 it's 29 in 100
+fn get_random_height_delta(rng: &mut Random, roughness: f64) -> i32 {
+    let flat = (100. - HEIGHT_CHANGE_CHANCE * roughness).clamp(0., 100.) as i32;
+    let roll = rng.generator.gen_range(0..100);
+    if roll < flat {
+        return 0;
     }
-}
 
-fn add_floor(commands: &mut Commands, game_textures: &Res<GameTextures>, world: &[(i32, usize)]) {
-    add_sprites(commands, game_textures, world);
-    add_colliders(world, commands);
-}
-
-fn add_sprites(commands: &mut Commands, game_textures: &Res<GameTextures>, world: &[(i32, usize)]) {
-    world.iter().for_each(|&(x, height)| {
-        add_column_of_tiles(
-            commands,
-            game_textures.floor.clone(),
-            x as f32,
-            0,
-            height as i32,
-        );
-    });
+    // the remaining rolls are split between the deltas in the original 10:10:9 ratio
+    match (roll - flat) * HEIGHT_CHANGE_CHANCE as i32 / (100 - flat) {
+        0..=9 => -1,
+        10..=19 => 1,
+        _ => 2,
+    }
 }
 
 fn add_column_of_tiles(
@@ -106,66 +231,85 @@ fn add_column_of_tiles(
     }
 }
 
-// Add colliders for the whole map as big rectangles
-fn add_colliders(world: &[(i32, usize)], commands: &mut Commands) {
+// Add colliders for columns of tiles standing on `bottom` as big rectangles, a block ends where
+// the height changes or where there's a gap between the columns
+pub fn add_colliders(world: &[(i32, usize)], bottom: f32, commands: &mut Commands) -> Vec<Entity> {
+    let mut colliders = Vec::new();
     let (mut block_start, mut current_height) =
         world.first().map(|&(x, y)| (x, y)).unwrap_or((0, 0));
+    let mut previous_x = block_start - 1;
 
     world.iter().for_each(|&(x, height_at_x)| {
-        if height_at_x != current_height {
-            spawn_static_collider_object(
+        if height_at_x != current_height || x != previous_x + 1 {
+            colliders.push(spawn_static_collider_object(
                 commands,
-                (block_start as f32 - HALF_TILE_SIZE, -HALF_TILE_SIZE),
+                (block_start as f32 - HALF_TILE_SIZE, bottom),
                 (
-                    x as f32 - HALF_TILE_SIZE,
+                    previous_x as f32 + HALF_TILE_SIZE,
                     current_height as f32 + HALF_TILE_SIZE,
                 ),
                 Wall,
-            );
+            ));
 
             block_start = x;
             current_height = height_at_x;
         }
+        previous_x = x;
     });
 
     if let Some(last_x) = world.last().map(|&(x, _)| x) {
-        spawn_static_collider_object(
+        colliders.push(spawn_static_collider_object(
             commands,
-            (block_start as f32 - HALF_TILE_SIZE, -HALF_TILE_SIZE),
+            (block_start as f32 - HALF_TILE_SIZE, bottom),
             (
                 last_x as f32 + HALF_TILE_SIZE,
                 current_height as f32 + HALF_TILE_SIZE,
             ),
             Wall,
-        );
+        ));
+    }
+    colliders
+}
+
+// Colliders are split at chunk boundaries, so that each of them can go away with its chunk
+pub fn add_chunk_colliders(world: &[(i32, usize)], commands: &mut Commands) {
+    for chunk in
+        world.chunk_by(|&(a, _), &(b, _)| a.div_euclid(CHUNK_WIDTH) == b.div_euclid(CHUNK_WIDTH))
+    {
+        add_colliders(chunk, -HALF_TILE_SIZE, commands);
     }
 }
 
-fn add_start_and_finish_line(
+fn add_start_wall(commands: &mut Commands, terrain: &mut Terrain) {
+    let start_x = -(BEGIN_WIDTH as i32);
+    terrain.set_column(start_x, 0, WALL_HEIGHT as i32);
+    spawn_wall(commands, start_x, WALL_HEIGHT);
+}
+
+// Wall standing on the bottom of the level, reaching up to `top`
+pub fn spawn_wall(commands: &mut Commands, x: i32, top: f32) -> Entity {
+    let x = x as f32;
+    spawn_static_collider_object(
+        commands,
+        (x - HALF_TILE_SIZE, -HALF_TILE_SIZE),
+        (x + HALF_TILE_SIZE, top - HALF_TILE_SIZE),
+        Wall,
+    )
+}
+
+// Returns the right edge of the level
+fn add_finish_line(
     commands: &mut Commands,
     game_textures: &Res<GameTextures>,
     world: &[(i32, usize)],
-) {
-    let (start_x, start_y) = (-(BEGIN_WIDTH as f32), 0.);
+    terrain: &mut Terrain,
+) -> f32 {
     let (finish_x, finish_y) = world
         .last()
         .map(|&(x, y)| (x as f32 + 1., y as f32))
         .unwrap_or((0., 0.));
 
-    add_column_of_tiles(
-        commands,
-        game_textures.floor.clone(),
-        start_x,
-        start_y as i32,
-        (start_y + WALL_HEIGHT) as i32,
-    );
-    add_column_of_tiles(
-        commands,
-        game_textures.floor.clone(),
-        finish_x,
-        start_y as i32,
-        finish_y as i32,
-    );
+    terrain.set_column(finish_x as i32, 0, finish_y as i32);
     add_column_of_tiles(
         commands,
         game_textures.finish_line.clone(),
@@ -174,19 +318,9 @@ fn add_start_and_finish_line(
         (finish_y + WALL_HEIGHT) as i32,
     );
 
-    spawn_static_collider_object(
-        commands,
-        (start_x - HALF_TILE_SIZE, start_y - HALF_TILE_SIZE),
-        (
-            start_x + HALF_TILE_SIZE,
-            start_y + WALL_HEIGHT - HALF_TILE_SIZE,
-        ),
-        Wall,
-    );
-
     let finish_entity = spawn_static_collider_object(
         commands,
-        (finish_x - HALF_TILE_SIZE, start_y - HALF_TILE_SIZE),
+        (finish_x - HALF_TILE_SIZE, -HALF_TILE_SIZE),
         (
             finish_x + HALF_TILE_SIZE,
             finish_y + WALL_HEIGHT - HALF_TILE_SIZE,
@@ -195,4 +329,17 @@ fn add_start_and_finish_line(
     );
 
     commands.entity(finish_entity).insert(FinishLine);
+    finish_x + HALF_TILE_SIZE
+}
+
+// The camera shows everything between the walls, from the bottom of the floor up to
+// well above the highest column
+fn level_bounds(world: &[(i32, usize)], right: f32) -> LevelBounds {
+    let highest = world.iter().map(|&(_, height)| height).max().unwrap_or(0);
+    LevelBounds {
+        left: -(BEGIN_WIDTH as f32) - HALF_TILE_SIZE,
+        right,
+        bottom: -HALF_TILE_SIZE,
+        top: highest as f32 + WALL_HEIGHT,
+    }
 }
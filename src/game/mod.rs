@@ -1,40 +1,82 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
+pub use animation::*;
+pub use archetypes::*;
 pub use audio::*;
+pub use biomes::*;
+pub use blocks::*;
+pub use brain::*;
 pub use bullets::*;
 pub use camera::*;
+pub use camera_effects::*;
 pub use components::*;
+pub use difficulty::*;
+pub use ects::*;
+pub use endless::*;
+pub use hazards::*;
+pub use loading::*;
 pub use map::*;
 pub use monster::*;
 pub use monster_ai::*;
+pub use parallax::*;
+pub use particles::*;
+pub use platforms::*;
 pub use player::*;
 pub use powerups::*;
+pub use progression::*;
+pub use ron_asset::*;
+pub use tilemap::*;
+pub use upgrades::*;
 pub use utils::*;
 
 use super::AppState;
 
+mod animation;
+mod archetypes;
 mod audio;
+mod biomes;
+mod blocks;
+mod brain;
 mod bullets;
 mod camera;
+mod camera_effects;
 mod components;
+mod difficulty;
+mod ects;
+mod endless;
+mod hazards;
+mod loading;
 mod map;
 mod monster;
 mod monster_ai;
+mod parallax;
+mod particles;
+mod platforms;
 mod player;
 mod powerups;
+mod progression;
+mod ron_asset;
+mod tilemap;
+mod upgrades;
 mod utils;
 
 pub struct GameTextures {
-    pub player: Handle<Image>,
+    pub player: Handle<TextureAtlas>,
     pub weak_bullet: Handle<Image>,
     pub strong_bullet: Handle<Image>,
     pub enemy_bullet: Handle<Image>,
-    pub bug: Handle<Image>,
-    pub valgrind: Handle<Image>,
     pub coffee: Handle<Image>,
     pub rust: Handle<Image>,
-    pub floor: Handle<Image>,
+    pub ects: Handle<Image>,
+    pub spikes: Handle<Image>,
+    pub lava: Handle<Image>,
+    pub falling_block: Handle<Image>,
+    pub platform: Handle<Image>,
+    pub brick: Handle<Image>,
+    pub question_block: Handle<Image>,
+    pub used_block: Handle<Image>,
+    pub tileset: Handle<Image>,
     pub finish_line: Handle<Image>,
 }
 
@@ -48,6 +90,7 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Random::new())
             .insert_resource(Level::new())
+            .insert_resource(Upgrades::new())
             .add_startup_system_to_stage(StartupStage::PreStartup, setup)
             .add_system_set(
                 SystemSet::on_update(AppState::InGame).with_system(back_to_main_menu_controls),
@@ -73,17 +116,32 @@ fn cleanup_all(mut commands: Commands, query: Query<Entity>) {
     }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let player_sheet = SpriteSheet {
+        texture: "images/sheets/player.png".to_string(),
+        frame_size: (48., 48.),
+    };
+    let player_texture = asset_server.load(player_sheet.texture.as_str());
     commands.insert_resource(GameTextures {
-        player: asset_server.load("images/player.png"),
+        player: texture_atlases.add(player_sheet.atlas(player_texture)),
         weak_bullet: asset_server.load("images/weak_bullet.png"),
         strong_bullet: asset_server.load("images/strong_bullet.png"),
         enemy_bullet: asset_server.load("images/enemy_bullet.png"),
-        bug: asset_server.load("images/bug.png"),
-        valgrind: asset_server.load("images/valgrind.png"),
         coffee: asset_server.load("images/coffee.png"),
         rust: asset_server.load("images/rust.png"),
-        floor: asset_server.load("images/cobblestone.png"),
+        ects: asset_server.load("images/ects.png"),
+        spikes: asset_server.load("images/spikes.png"),
+        lava: asset_server.load("images/lava.png"),
+        falling_block: asset_server.load("images/falling_block.png"),
+        platform: asset_server.load("images/platform.png"),
+        brick: asset_server.load("images/brick.png"),
+        question_block: asset_server.load("images/question_block.png"),
+        used_block: asset_server.load("images/used_block.png"),
+        tileset: asset_server.load("images/tilesets/cobblestone.png"),
         finish_line: asset_server.load("images/finish_line.png"),
     });
     commands
@@ -4,47 +4,70 @@ use rand::Rng;
 
 use crate::game::utils::*;
 use crate::game::{
-    Bug, DeadPlayerEvent, Enemy, EnemyBullet, Jumper, Player, Valgrind, SAFE_ZONE_WIDTH,
+    AiTimer, Animator, Brain, DeadPlayerEvent, DifficultyFactors, Enemy, EnemyArchetype,
+    EnemyArchetypes, EnemyAssets, EnemyBullet, EnemySpawn, Flyer, Jumper, Movement, Player,
+    Shooter, SAFE_ZONE_WIDTH,
 };
-use crate::{GameTextures, Level, Random};
+use crate::{Level, Random};
 
-const SPAWNING_PROBABILITY: f64 = 0.05;
+const COLLIDER_BORDER_RADIUS: f32 = 0.1;
+const GROUND_SPAWN_HEIGHT: f32 = 1.5;
 
-fn spawn_enemy<T>(commands: &mut Commands, texture: Handle<Image>, enemy_type: T, x: f32, y: f32)
-where
-    T: Component,
-{
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    enemy_assets: &EnemyAssets,
+    archetype: &EnemyArchetype,
+    factors: &DifficultyFactors,
+    rng: &mut Random,
+    x: f32,
+    y: f32,
+) {
+    // an enemy without its sprites would be invisible, so it isn't spawned at all
+    let atlas = match enemy_assets.atlas(archetype) {
+        Some(atlas) => atlas,
+        None => return,
+    };
     let mut enemy_entity = spawn_dynamic_object(
         commands,
-        create_sprite_bundle(texture, (0.9, 0.9), (x, y, 10.0)),
+        create_sprite_sheet_bundle(atlas, archetype.size, (x, y, 10.0)),
         None,
         None,
     );
+    let (collider_x, collider_y) = archetype.collider;
     enemy_entity = spawn_solid_collider(
         commands,
         enemy_entity,
-        Collider::round_cuboid(0.25, 0.25, 0.1),
+        Collider::round_cuboid(collider_x, collider_y, COLLIDER_BORDER_RADIUS),
         None,
     );
     commands
         .entity(enemy_entity)
-        .insert(Enemy::default())
-        .insert(Jumper::default())
-        .insert(enemy_type);
-}
+        .insert(Name::new(archetype.name.clone()))
+        .insert(Enemy {
+            speed: archetype.speed * factors.enemy_speed as f32,
+            health: archetype.health,
+            score: archetype.score,
+            ..default()
+        })
+        .insert(Jumper {
+            jump_impulse: archetype.jump_impulse,
+            is_jumping: false,
+        })
+        .insert(Brain::new(archetype.ai))
+        .insert(Animator::default())
+        .insert(AiTimer::new(archetype.ai_cadence, rng));
 
-fn spawn_bug(commands: &mut Commands, game_textures: &Res<GameTextures>, x: f32, y: f32) {
-    spawn_enemy(commands, game_textures.bug.clone(), Bug::default(), x, y);
-}
-
-fn spawn_valgrind(commands: &mut Commands, game_textures: &Res<GameTextures>, x: f32, y: f32) {
-    spawn_enemy(
-        commands,
-        game_textures.valgrind.clone(),
-        Valgrind::default(),
-        x,
-        y,
-    );
+    if let Some(weapon) = &archetype.weapon {
+        commands.entity(enemy_entity).insert(Shooter::new(
+            weapon.cooldown / factors.shot_frequency as f32,
+        ));
+    }
+    if let Movement::Flying { swoop_interval, .. } = archetype.movement {
+        commands
+            .entity(enemy_entity)
+            .insert(GravityScale(0.0))
+            .insert(Flyer::new(y, swoop_interval));
+    }
 }
 
 pub fn death_by_enemy(
@@ -77,27 +100,42 @@ pub fn death_by_enemy(
 pub fn add_enemies(
     commands: &mut Commands,
     world: &[(i32, usize)],
-    game_textures: &Res<GameTextures>,
-    rng: &mut ResMut<Random>,
+    enemy_assets: &EnemyAssets,
+    archetypes: &EnemyArchetypes,
+    spawns: &[EnemySpawn],
+    rng: &mut Random,
     level: &Res<Level>,
 ) {
-    // every 3 levels we change the kinds of enemies.
-    let (should_add_bug, should_add_valgrind) = (level.level % 3 != 2, level.level % 3 != 1);
+    let factors = &level.factors;
+    for spawn in spawns.iter() {
+        let (archetype, probability) = match archetypes.get(&spawn.archetype) {
+            Some(archetype) => (archetype, spawn.density),
+            None => continue,
+        };
 
-    world.iter().for_each(|&(x, height)| {
-        if should_add_bug && should_add_enemy(x, rng, level) {
-            spawn_bug(commands, game_textures, x as f32, height as f32 + 1.5);
+        match archetype.movement {
+            Movement::Walking => world.iter().for_each(|&(x, height)| {
+                if should_add_enemy(x, rng, level, probability) {
+                    let y = height as f32 + GROUND_SPAWN_HEIGHT;
+                    spawn_enemy(commands, enemy_assets, archetype, factors, rng, x as f32, y);
+                }
+            }),
+            Movement::Flying { hover_height, .. } => world.windows(2).for_each(|window| {
+                let ((x, height), (_, next_height)) = (window[0], window[1]);
+                if height != next_height && should_add_enemy(x, rng, level, probability) {
+                    let y = height.max(next_height) as f32 + hover_height;
+                    let x = x as f32 + 0.5;
+                    spawn_enemy(commands, enemy_assets, archetype, factors, rng, x, y);
+                }
+            }),
         }
-        if should_add_valgrind && should_add_enemy(x, rng, level) {
-            spawn_valgrind(commands, game_textures, x as f32, height as f32 + 1.5);
-        }
-    });
+    }
 }
 
-fn should_add_enemy(x: i32, rng: &mut ResMut<Random>, level: &Res<Level>) -> bool {
+fn should_add_enemy(x: i32, rng: &mut Random, level: &Res<Level>, probability: f64) -> bool {
     if x <= SAFE_ZONE_WIDTH as i32 {
         return false;
     }
     rng.generator
-        .gen_bool(SPAWNING_PROBABILITY * level.difficulty)
+        .gen_bool((probability * level.factors.enemy_density).min(1.))
 }
@@ -1,18 +1,42 @@
-use bevy::core::FixedTimestep;
+use std::time::Duration;
+
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use rand::Rng;
 
-use crate::game::{spawn_enemy_bullet, BulletOptions, Enemy, Powerup, Valgrind};
-use crate::{GameTextures, Random};
+use crate::game::{
+    spawn_enemy_bullet, AiState, AiTimer, Animator, Brain, BulletOptions, Clip, Enemy, FirePattern,
+    Flyer, ParticleEffect, ParticleEvent, Perception, PlaySfx, Player, Powerup, Shooter, Wall,
+    Weapon,
+};
+use crate::{GameTextures, Level, Random};
 
 use super::super::AppState;
 use super::{GameDirection, Jumper};
 
-const JUMP_PROBABILITY: f64 = 0.25;
-const SHOOT_PROBABILITY: f64 = 0.25;
-const CHANGE_DIRECTION_PROBABILITY: f64 = 0.25;
-const ACTION_TIMESTEP: f64 = 2.0;
+const SIGHT_DISTANCE: f32 = 15.0;
+const STUN_COLOR: Color = Color::GRAY;
+const GROUND_CHECK_DISTANCE: f32 = 0.5;
+const LEDGE_LOOKAHEAD: f32 = 0.6;
+const MAX_SAFE_DROP: f32 = 1.5;
+const OBSTACLE_CHECK_DISTANCE: f32 = 0.6;
+const CHASE_MIN_DISTANCE: f32 = 0.5;
+const SHOOTING_MAX_HEIGHT_DIFFERENCE: f32 = 2.0;
+const WINDUP_DURATION: f32 = 0.6;
+const WINDUP_FLASH_FREQUENCY: f32 = 20.0;
+const WINDUP_COLOR: Color = Color::RED;
+const BURST_SIZE: usize = 3;
+const BURST_INTERVAL: f32 = 0.15;
+const SPREAD_ANGLE: f32 = 0.3;
+const FLYER_WAVE_AMPLITUDE: f32 = 1.0;
+const FLYER_WAVE_FREQUENCY: f32 = 2.0;
+const FLYER_FOLLOW_STRENGTH: f32 = 3.0;
+const FLYER_SWOOP_SPEED: f32 = 7.0;
+const FLYER_SWOOP_END_DISTANCE: f32 = 0.5;
+// a swoop blocked by terrain is given up after this long
+const FLYER_MAX_SWOOP_DURATION: f32 = 1.5;
+// systems reading whether an AI timer just finished run after it's ticked
+const TICK_AI_TIMERS: &str = "tick_ai_timers";
 
 pub struct MonsterAiPlugin;
 struct MonsterCollisionEvent {
@@ -23,64 +47,432 @@ impl Plugin for MonsterAiPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(
             SystemSet::on_update(AppState::InGame)
+                .with_system(tick_ai_timers.label(TICK_AI_TIMERS))
+                .with_system(monster_thinks)
+                .with_system(monster_changes_direction_randomly.after(TICK_AI_TIMERS))
+                .with_system(monster_jumps.after(TICK_AI_TIMERS))
                 .with_system(monster_walks)
+                .with_system(monster_steers)
                 .with_system(monster_contact_detection)
-                .with_system(monster_change_direction_on_contact),
-        )
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(ACTION_TIMESTEP))
-                .with_system(monster_changes_direction_randomly)
-                .with_system(monster_jumps)
-                .with_system(valgrind_shoots),
+                .with_system(monster_change_direction_on_contact)
+                .with_system(flyer_flies)
+                .with_system(flyer_swoops)
+                .with_system(shooter_aims)
+                .with_system(shooter_winds_up)
+                .with_system(shooter_fires_burst),
         )
         .add_event::<MonsterCollisionEvent>();
     }
 }
 
-fn monster_walks(mut monsters: Query<(&Enemy, &mut Velocity)>) {
-    for (monster, mut velocity) in monsters.iter_mut() {
+fn tick_ai_timers(time: Res<Time>, mut rng: ResMut<Random>, mut ai_timers: Query<&mut AiTimer>) {
+    for mut ai_timer in ai_timers.iter_mut() {
+        if ai_timer.timer.tick(time.delta()).just_finished() {
+            let next_duration = rng.jitter(ai_timer.cadence);
+            ai_timer
+                .timer
+                .set_duration(Duration::from_secs_f32(next_duration));
+        }
+    }
+}
+
+fn monster_thinks(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    walls: Query<Entity, With<Wall>>,
+    players: Query<(&Transform, &Player)>,
+    mut monsters: Query<(&mut Brain, &Transform, &mut TextureAtlasSprite), Without<Player>>,
+) {
+    let player = players.get_single().ok();
+
+    for (mut brain, transform, mut sprite) in monsters.iter_mut() {
+        let position = transform.translation.truncate();
+        let perception = match player {
+            Some((player_transform, player)) => {
+                let player_position = player_transform.translation.truncate();
+                let distance = position.distance(player_position);
+                Perception {
+                    distance_to_player: Some(distance),
+                    sees_player: distance < SIGHT_DISTANCE
+                        && line_of_sight(&rapier_context, &walls, position, player_position),
+                    player_is_dangerous: matches!(player.weapon, Weapon::StrongBullet),
+                }
+            }
+            None => Perception {
+                distance_to_player: None,
+                sees_player: false,
+                player_is_dangerous: false,
+            },
+        };
+
+        brain.update(time.delta(), &perception);
+
+        if brain.is(AiState::Stunned) {
+            sprite.color = STUN_COLOR;
+        } else if sprite.color == STUN_COLOR {
+            sprite.color = Color::WHITE;
+        }
+    }
+}
+
+fn monster_walks(mut monsters: Query<(&Enemy, &Brain, &mut Velocity), Without<Flyer>>) {
+    for (monster, brain, mut velocity) in monsters.iter_mut() {
+        let speed = match (brain.state, monster.direction) {
+            (AiState::Attack | AiState::Stunned, _) => 0.,
+            _ if monster.waiting_at_ledge => 0.,
+            (_, GameDirection::Left) => -monster.speed,
+            (_, GameDirection::Right) => monster.speed,
+        };
+
+        velocity.linvel = Vec2::new(speed, velocity.linvel.y);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn monster_steers(
+    rapier_context: Res<RapierContext>,
+    walls: Query<Entity, With<Wall>>,
+    players: Query<&Transform, With<Player>>,
+    mut monsters: Query<(&mut Enemy, &Brain, &Transform), (Without<Player>, Without<Flyer>)>,
+) {
+    let player_position = players
+        .get_single()
+        .map(|player| player.translation.truncate());
+
+    for (mut monster, brain, transform) in monsters.iter_mut() {
+        let position = transform.translation.truncate();
+        monster.waiting_at_ledge = false;
+
+        // standing enemies and the ones in the middle of a jump have nowhere to go
+        if brain.is(AiState::Attack)
+            || brain.is(AiState::Stunned)
+            || cast_wall_ray(
+                &rapier_context,
+                &walls,
+                position,
+                -Vec2::Y,
+                GROUND_CHECK_DISTANCE,
+            )
+            .is_none()
+        {
+            continue;
+        }
+
+        if let Ok(player_position) = player_position {
+            let to_player = player_position - position;
+            if to_player.x.abs() > CHASE_MIN_DISTANCE {
+                let towards_player = if to_player.x < 0. {
+                    GameDirection::Left
+                } else {
+                    GameDirection::Right
+                };
+                match brain.state {
+                    AiState::Chase => monster.direction = towards_player,
+                    AiState::Flee => monster.direction = opposite(towards_player),
+                    _ => {}
+                }
+            }
+        }
+
+        let ahead = match monster.direction {
+            GameDirection::Left => -Vec2::X,
+            GameDirection::Right => Vec2::X,
+        };
+        let is_ledge_ahead = cast_wall_ray(
+            &rapier_context,
+            &walls,
+            position + ahead * LEDGE_LOOKAHEAD,
+            -Vec2::Y,
+            MAX_SAFE_DROP,
+        )
+        .is_none();
+        let is_obstacle_ahead = cast_wall_ray(
+            &rapier_context,
+            &walls,
+            position,
+            ahead,
+            OBSTACLE_CHECK_DISTANCE,
+        )
+        .is_some();
+
+        // turning away from the player would only be undone by the chase next frame
+        monster.waiting_at_ledge = is_ledge_ahead && brain.is(AiState::Chase);
+        if (is_ledge_ahead || is_obstacle_ahead) && !monster.waiting_at_ledge {
+            change_direction(monster);
+        }
+    }
+}
+
+// Returns the distance to the closest wall hit by the ray
+fn cast_wall_ray(
+    rapier_context: &RapierContext,
+    walls: &Query<Entity, With<Wall>>,
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: f32,
+) -> Option<f32> {
+    rapier_context
+        .cast_ray(
+            origin,
+            direction,
+            max_distance,
+            true,
+            InteractionGroups::all(),
+            Some(&|entity| walls.get(entity).is_ok()),
+        )
+        .map(|(_, distance)| distance)
+}
+
+fn line_of_sight(
+    rapier_context: &RapierContext,
+    walls: &Query<Entity, With<Wall>>,
+    from: Vec2,
+    to: Vec2,
+) -> bool {
+    let distance = from.distance(to);
+    distance == 0.
+        || cast_wall_ray(
+            rapier_context,
+            walls,
+            from,
+            (to - from) / distance,
+            distance,
+        )
+        .is_none()
+}
+
+fn flyer_flies(
+    time: Res<Time>,
+    mut flyers: Query<(&mut Flyer, &Enemy, &Brain, &Transform, &mut Velocity)>,
+) {
+    for (mut flyer, monster, brain, transform, mut velocity) in flyers.iter_mut() {
+        let position = transform.translation.truncate();
+
+        if brain.is(AiState::Stunned) {
+            flyer.swoop_target = None;
+            velocity.linvel = Vec2::ZERO;
+            continue;
+        }
+
+        if let Some(target) = flyer.swoop_target {
+            let to_target = target - position;
+            flyer.swoop_time += time.delta_seconds();
+            if to_target.length() > FLYER_SWOOP_END_DISTANCE
+                && flyer.swoop_time < FLYER_MAX_SWOOP_DURATION
+            {
+                velocity.linvel = to_target.normalize() * FLYER_SWOOP_SPEED;
+                continue;
+            }
+            flyer.swoop_target = None;
+        }
+
+        // after a swoop the flyer smoothly comes back to its patrol height
+        flyer.phase += time.delta_seconds() * FLYER_WAVE_FREQUENCY;
+        let wave_y = flyer.origin_y + FLYER_WAVE_AMPLITUDE * flyer.phase.sin();
         let speed = match monster.direction {
             GameDirection::Left => -monster.speed,
             GameDirection::Right => monster.speed,
         };
 
-        velocity.linvel = Vec2::new(speed, velocity.linvel.y);
+        velocity.linvel = Vec2::new(speed, (wave_y - position.y) * FLYER_FOLLOW_STRENGTH);
+    }
+}
+
+fn flyer_swoops(
+    time: Res<Time>,
+    players: Query<&Transform, With<Player>>,
+    mut flyers: Query<(&mut Flyer, &mut Enemy, &Brain, &Transform), Without<Player>>,
+) {
+    if let Ok(player) = players.get_single() {
+        let player_position = player.translation.truncate();
+
+        for (mut flyer, mut monster, brain, transform) in flyers.iter_mut() {
+            let position = transform.translation.truncate();
+            if !flyer.swoop_timer.tick(time.delta()).just_finished()
+                || flyer.swoop_target.is_some()
+                || !brain.is(AiState::Attack)
+                || player_position.y > position.y
+            {
+                continue;
+            }
+
+            monster.direction = if player_position.x < position.x {
+                GameDirection::Left
+            } else {
+                GameDirection::Right
+            };
+            flyer.swoop_target = Some(player_position);
+            flyer.swoop_time = 0.;
+        }
+    }
+}
+
+fn shooter_aims(
+    time: Res<Time>,
+    players: Query<&Transform, With<Player>>,
+    mut shooters: Query<(&mut Shooter, &mut Enemy, &Brain, &Transform), Without<Player>>,
+) {
+    if let Ok(player) = players.get_single() {
+        let player_position = player.translation.truncate();
+
+        for (mut shooter, mut monster, brain, transform) in shooters.iter_mut() {
+            let to_player = player_position - transform.translation.truncate();
+            shooter.cooldown.tick(time.delta());
+
+            if !shooter.cooldown.finished()
+                || shooter.windup.is_some()
+                || shooter.burst_left > 0
+                || !brain.is(AiState::Attack)
+                || to_player.y.abs() > SHOOTING_MAX_HEIGHT_DIFFERENCE
+            {
+                continue;
+            }
+
+            shooter.aim = if to_player.x < 0. {
+                GameDirection::Left
+            } else {
+                GameDirection::Right
+            };
+            monster.direction = shooter.aim;
+            shooter.windup = Some(Timer::from_seconds(WINDUP_DURATION, false));
+        }
     }
 }
 
-fn valgrind_shoots(
+#[allow(clippy::too_many_arguments)]
+fn shooter_winds_up(
     mut commands: Commands,
+    time: Res<Time>,
     game_textures: Res<GameTextures>,
-    positions: Query<(&mut Transform, &RigidBody, &mut Enemy, &mut Velocity), With<Valgrind>>,
+    level: Res<Level>,
     mut rng: ResMut<Random>,
+    mut shooters: Query<(
+        &mut Shooter,
+        &Brain,
+        &Transform,
+        &mut TextureAtlasSprite,
+        &mut Animator,
+    )>,
+    mut send_particle_event: EventWriter<ParticleEvent>,
+    mut send_sfx: EventWriter<PlaySfx>,
 ) {
-    for (pos, _, valgrind, vel) in positions.iter() {
-        let options = BulletOptions {
-            x: pos.translation.x,
-            y: pos.translation.y,
-            direction: valgrind.direction,
-            player_vex: vel.linvel.x,
+    for (mut shooter, brain, transform, mut sprite, mut animator) in shooters.iter_mut() {
+        // a stunned enemy loses its concentration
+        if brain.is(AiState::Stunned) {
+            shooter.windup = None;
+            shooter.burst_left = 0;
+            continue;
+        }
+
+        let windup_finished = match shooter.windup.as_mut() {
+            Some(windup) => windup.tick(time.delta()).finished(),
+            None => continue,
         };
-        if should_shoot(&mut rng) {
-            spawn_enemy_bullet(&mut commands, &game_textures, options);
+
+        if !windup_finished {
+            let elapsed = shooter
+                .windup
+                .as_ref()
+                .map_or(0., |windup| windup.elapsed_secs());
+            sprite.color = if (elapsed * WINDUP_FLASH_FREQUENCY).sin() > 0. {
+                WINDUP_COLOR
+            } else {
+                Color::WHITE
+            };
+            continue;
+        }
+
+        sprite.color = Color::WHITE;
+        animator.play(Clip::Shoot);
+        shooter.windup = None;
+        shooter.cooldown = Timer::from_seconds(rng.jitter(shooter.reload), false);
+        shooter.pattern = choose_fire_pattern(&mut rng, &level);
+
+        let options = bullet_options(&shooter, transform);
+        send_particle_event.send(ParticleEvent::new(
+            ParticleEffect::MuzzleFlash(shooter.aim),
+            options.muzzle(),
+        ));
+        send_sfx.send(PlaySfx::at("enemy_shoot", options.muzzle()));
+        match shooter.pattern {
+            FirePattern::Single => spawn_enemy_bullet(&mut commands, &game_textures, options, 0.),
+            FirePattern::Burst => {
+                spawn_enemy_bullet(&mut commands, &game_textures, options, 0.);
+                shooter.burst_left = BURST_SIZE - 1;
+                shooter.burst_timer = Timer::from_seconds(BURST_INTERVAL, false);
+            }
+            FirePattern::Spread => {
+                for angle in [-SPREAD_ANGLE, 0., SPREAD_ANGLE] {
+                    spawn_enemy_bullet(&mut commands, &game_textures, options, angle);
+                }
+            }
         }
     }
 }
 
-fn change_direction(mut monster: Mut<Enemy>) {
-    monster.direction = match monster.direction {
+fn shooter_fires_burst(
+    mut commands: Commands,
+    time: Res<Time>,
+    game_textures: Res<GameTextures>,
+    mut shooters: Query<(&mut Shooter, &Transform)>,
+    mut send_particle_event: EventWriter<ParticleEvent>,
+    mut send_sfx: EventWriter<PlaySfx>,
+) {
+    for (mut shooter, transform) in shooters.iter_mut() {
+        if shooter.burst_left == 0 || !shooter.burst_timer.tick(time.delta()).finished() {
+            continue;
+        }
+
+        let options = bullet_options(&shooter, transform);
+        send_particle_event.send(ParticleEvent::new(
+            ParticleEffect::MuzzleFlash(shooter.aim),
+            options.muzzle(),
+        ));
+        send_sfx.send(PlaySfx::at("enemy_shoot", options.muzzle()));
+        spawn_enemy_bullet(&mut commands, &game_textures, options, 0.);
+        shooter.burst_left -= 1;
+        shooter.burst_timer.reset();
+    }
+}
+
+fn bullet_options(shooter: &Shooter, transform: &Transform) -> BulletOptions {
+    BulletOptions {
+        x: transform.translation.x,
+        y: transform.translation.y,
+        direction: shooter.aim,
+        player_vex: 0.,
+    }
+}
+
+// The harder the level, the more dangerous patterns can be used
+fn choose_fire_pattern(rng: &mut Random, level: &Res<Level>) -> FirePattern {
+    let patterns = [FirePattern::Single, FirePattern::Burst, FirePattern::Spread];
+    let available = (level.factors.tier + 1).min(patterns.len());
+    patterns[rng.generator.gen_range(0..available)]
+}
+
+fn opposite(direction: GameDirection) -> GameDirection {
+    match direction {
         GameDirection::Left => GameDirection::Right,
         GameDirection::Right => GameDirection::Left,
     }
 }
 
+fn change_direction(mut monster: Mut<Enemy>) {
+    monster.direction = opposite(monster.direction);
+}
+
 fn monster_changes_direction_randomly(
-    mut monster_query: Query<&mut Enemy>,
+    mut monster_query: Query<(&mut Enemy, &Brain, &AiTimer)>,
     mut rng: ResMut<Random>,
 ) {
-    for monster in monster_query.iter_mut() {
-        if should_change_direction(&mut rng) {
+    for (monster, brain, ai_timer) in monster_query.iter_mut() {
+        if ai_timer.timer.just_finished()
+            && brain.is(AiState::Patrol)
+            && rng
+                .generator
+                .gen_bool(brain.profile.change_direction_probability)
+        {
             change_direction(monster);
         }
     }
@@ -119,25 +511,16 @@ fn monster_change_direction_on_contact(
 }
 
 fn monster_jumps(
-    mut monsters: Query<(&mut Jumper, &mut Velocity), With<Enemy>>,
+    mut monsters: Query<(&mut Jumper, &mut Velocity, &Brain, &AiTimer), Without<Flyer>>,
     mut rng: ResMut<Random>,
 ) {
-    for (mut jumper, mut velocity) in monsters.iter_mut() {
-        if should_jump(&mut rng) {
+    for (mut jumper, mut velocity, brain, ai_timer) in monsters.iter_mut() {
+        if ai_timer.timer.just_finished()
+            && (brain.is(AiState::Patrol) || brain.is(AiState::Chase))
+            && rng.generator.gen_bool(brain.profile.jump_probability)
+        {
             velocity.linvel = Vec2::new(0., jumper.jump_impulse);
             jumper.is_jumping = true
         }
     }
 }
-
-fn should_change_direction(rng: &mut ResMut<Random>) -> bool {
-    rng.generator.gen_bool(CHANGE_DIRECTION_PROBABILITY)
-}
-
-fn should_jump(rng: &mut ResMut<Random>) -> bool {
-    rng.generator.gen_bool(JUMP_PROBABILITY)
-}
-
-fn should_shoot(rng: &mut ResMut<Random>) -> bool {
-    rng.generator.gen_bool(SHOOT_PROBABILITY)
-}
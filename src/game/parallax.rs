@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use bevy::render::camera::Camera2d;
+use bevy::transform::TransformSystem;
+use serde::Deserialize;
+
+use crate::game::{Biome, BiomeAssets};
+
+use super::utils::*;
+
+// The camera only sees z above -0.1 and the map is drawn at 0, the layers go in between
+const PARALLAX_Z: f32 = -0.09;
+const PARALLAX_Z_STEP: f32 = 0.001;
+// a layer must be at least half as wide as the view to cover it with three tiles
+const TILES_PER_LAYER: i32 = 3;
+
+pub struct ParallaxPlugin;
+
+impl Plugin for ParallaxPlugin {
+    fn build(&self, app: &mut App) {
+        // the camera is done moving by then, so the layers never lag a frame behind it
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            scroll_parallax.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+// Layers are listed back to front. `follow` is the part of the camera movement a layer
+// follows, 1 stays still on the screen and 0 moves together with the map.
+#[derive(Deserialize)]
+pub struct ParallaxLayerSpec {
+    pub texture: String,
+    pub size: (f32, f32),
+    pub y: f32,
+    pub follow: f32,
+}
+
+#[derive(Component)]
+pub struct ParallaxLayer {
+    follow: f32,
+    width: f32,
+    y: f32,
+    tile: i32,
+}
+
+pub fn add_parallax_layers(commands: &mut Commands, biome: &Biome, biome_assets: &BiomeAssets) {
+    for (i, spec) in biome.layers.iter().enumerate() {
+        let texture = biome_assets
+            .textures
+            .get(&spec.texture)
+            .cloned()
+            .unwrap_or_default();
+        let z = PARALLAX_Z + i as f32 * PARALLAX_Z_STEP;
+        for tile in 0..TILES_PER_LAYER {
+            commands
+                .spawn_bundle(create_sprite_bundle(
+                    texture.clone(),
+                    spec.size,
+                    (0., spec.y, z),
+                ))
+                .insert(ParallaxLayer {
+                    follow: spec.follow,
+                    width: spec.size.0,
+                    y: spec.y,
+                    tile,
+                });
+        }
+    }
+}
+
+// Tiles of a layer are laid side by side around the camera, so the layer never ends
+fn scroll_parallax(
+    cameras: Query<&Transform, With<Camera2d>>,
+    mut layers: Query<(&ParallaxLayer, &mut Transform), Without<Camera2d>>,
+) {
+    let camera = match cameras.get_single() {
+        Ok(camera) => camera.translation,
+        Err(_) => return,
+    };
+
+    for (layer, mut transform) in layers.iter_mut() {
+        let origin = camera.x * layer.follow;
+        let first = ((camera.x - origin) / layer.width).round() as i32 - TILES_PER_LAYER / 2;
+        transform.translation.x = origin + (first + layer.tile) as f32 * layer.width;
+        transform.translation.y = camera.y * layer.follow + layer.y;
+    }
+}
@@ -0,0 +1,260 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use rand::{thread_rng, Rng};
+
+use crate::game::{GameDirection, Player};
+use crate::AppState;
+
+const PARTICLE_TEXTURE: &str = "images/particle.png";
+// particles are taken from a pool, effects are cut short once it runs dry
+const POOL_SIZE: usize = 256;
+const PARTICLE_Z: f32 = 20.0;
+const STEAM_INTERVAL: f32 = 0.12;
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ParticlePool>()
+            .add_startup_system(load_particle_texture)
+            .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(fill_particle_pool))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(emit_particles)
+                    .with_system(emit_coffee_steam)
+                    .with_system(update_particles),
+            )
+            .add_event::<ParticleEvent>();
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum ParticleEffect {
+    MuzzleFlash(GameDirection),
+    ImpactSparks,
+    DeathBurst,
+    CoffeeSteam,
+    PickupSparkle,
+}
+
+pub struct ParticleEvent {
+    pub effect: ParticleEffect,
+    pub position: Vec2,
+}
+
+impl ParticleEvent {
+    pub fn new(effect: ParticleEffect, position: Vec3) -> Self {
+        ParticleEvent {
+            effect,
+            position: position.truncate(),
+        }
+    }
+}
+
+// How a single effect spreads its particles
+struct Emitter {
+    count: usize,
+    // direction of the middle particle and the angle the rest spread over, in radians
+    angle: f32,
+    spread: f32,
+    speed: (f32, f32),
+    lifetime: (f32, f32),
+    size: (f32, f32),
+    gravity: f32,
+    color: Color,
+}
+
+impl ParticleEffect {
+    fn emitter(&self) -> Emitter {
+        match self {
+            ParticleEffect::MuzzleFlash(direction) => Emitter {
+                count: 6,
+                angle: match direction {
+                    GameDirection::Left => PI,
+                    GameDirection::Right => 0.,
+                },
+                spread: 0.8,
+                speed: (3., 6.),
+                lifetime: (0.05, 0.12),
+                size: (0.25, 0.05),
+                gravity: 0.,
+                color: Color::rgb(1., 0.85, 0.4),
+            },
+            ParticleEffect::ImpactSparks => Emitter {
+                count: 8,
+                angle: PI / 2.,
+                spread: 2. * PI,
+                speed: (2., 5.),
+                lifetime: (0.15, 0.3),
+                size: (0.15, 0.02),
+                gravity: 15.,
+                color: Color::rgb(1., 0.7, 0.3),
+            },
+            ParticleEffect::DeathBurst => Emitter {
+                count: 24,
+                angle: PI / 2.,
+                spread: 2. * PI,
+                speed: (2., 7.),
+                lifetime: (0.3, 0.6),
+                size: (0.3, 0.05),
+                gravity: 8.,
+                color: Color::rgb(0.6, 0.9, 0.3),
+            },
+            ParticleEffect::CoffeeSteam => Emitter {
+                count: 1,
+                angle: PI / 2.,
+                spread: 0.6,
+                speed: (0.5, 1.2),
+                lifetime: (0.6, 1.0),
+                size: (0.15, 0.35),
+                gravity: -0.5,
+                color: Color::rgba(0.9, 0.9, 0.9, 0.6),
+            },
+            ParticleEffect::PickupSparkle => Emitter {
+                count: 12,
+                angle: PI / 2.,
+                spread: 2. * PI,
+                speed: (1., 3.),
+                lifetime: (0.3, 0.5),
+                size: (0.2, 0.),
+                gravity: -2.,
+                color: Color::rgb(1., 1., 0.6),
+            },
+        }
+    }
+}
+
+struct ParticleTexture(Handle<Image>);
+
+#[derive(Default)]
+struct ParticlePool {
+    free: Vec<Entity>,
+}
+
+#[derive(Component, Default)]
+struct Particle {
+    velocity: Vec2,
+    gravity: f32,
+    age: f32,
+    lifetime: f32,
+    size: (f32, f32),
+    color: Color,
+}
+
+fn load_particle_texture(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ParticleTexture(asset_server.load(PARTICLE_TEXTURE)));
+}
+
+// The level's entities are all despawned when it ends, so the pool is refilled for each one
+fn fill_particle_pool(
+    mut commands: Commands,
+    texture: Res<ParticleTexture>,
+    mut pool: ResMut<ParticlePool>,
+) {
+    pool.free = (0..POOL_SIZE)
+        .map(|_| {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    texture: texture.0.clone(),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(Particle::default())
+                .id()
+        })
+        .collect();
+}
+
+fn emit_particles(
+    mut pool: ResMut<ParticlePool>,
+    mut particles: Query<(&mut Particle, &mut Transform, &mut Sprite, &mut Visibility)>,
+    mut events: EventReader<ParticleEvent>,
+) {
+    let mut rng = thread_rng();
+    for event in events.iter() {
+        let emitter = event.effect.emitter();
+        for _ in 0..emitter.count {
+            let entity = match pool.free.pop() {
+                Some(entity) => entity,
+                None => break,
+            };
+            if let Ok((mut particle, mut transform, mut sprite, mut visibility)) =
+                particles.get_mut(entity)
+            {
+                let angle = emitter.angle + emitter.spread * (rng.gen::<f32>() - 0.5);
+                let speed = rng.gen_range(emitter.speed.0..=emitter.speed.1);
+                *particle = Particle {
+                    velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+                    gravity: emitter.gravity,
+                    age: 0.,
+                    lifetime: rng.gen_range(emitter.lifetime.0..=emitter.lifetime.1),
+                    size: emitter.size,
+                    color: emitter.color,
+                };
+                transform.translation = event.position.extend(PARTICLE_Z);
+                sprite.custom_size = Some(Vec2::splat(emitter.size.0));
+                sprite.color = emitter.color;
+                visibility.is_visible = true;
+            }
+        }
+    }
+}
+
+fn emit_coffee_steam(
+    time: Res<Time>,
+    mut elapsed: Local<f32>,
+    players: Query<(&Transform, &Player)>,
+    mut send_particle_event: EventWriter<ParticleEvent>,
+) {
+    if let Ok((transform, player)) = players.get_single() {
+        if player.speed <= player.base_speed {
+            return;
+        }
+        *elapsed += time.delta_seconds();
+        while *elapsed >= STEAM_INTERVAL {
+            *elapsed -= STEAM_INTERVAL;
+            send_particle_event.send(ParticleEvent::new(
+                ParticleEffect::CoffeeSteam,
+                transform.translation + Vec3::new(0., 0.4, 0.),
+            ));
+        }
+    }
+}
+
+// Particles shrink and fade out over their lifetime and go back to the pool at its end
+fn update_particles(
+    time: Res<Time>,
+    mut pool: ResMut<ParticlePool>,
+    mut particles: Query<(
+        Entity,
+        &mut Particle,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut particle, mut transform, mut sprite, mut visibility) in particles.iter_mut() {
+        if !visibility.is_visible {
+            continue;
+        }
+
+        particle.age += delta;
+        if particle.age >= particle.lifetime {
+            visibility.is_visible = false;
+            pool.free.push(entity);
+            continue;
+        }
+
+        particle.velocity.y -= particle.gravity * delta;
+        transform.translation += (particle.velocity * delta).extend(0.);
+
+        let progress = particle.age / particle.lifetime;
+        let (start_size, end_size) = particle.size;
+        let size = start_size + (end_size - start_size) * progress;
+        sprite.custom_size = Some(Vec2::splat(size));
+        sprite.color = particle.color;
+        sprite.color.set_a(particle.color.a() * (1. - progress));
+    }
+}
@@ -0,0 +1,240 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::game::{Enemy, Player, Wall, SAFE_ZONE_WIDTH};
+use crate::{AppState, GameTextures, Level, Random};
+
+use super::utils::*;
+
+const PLATFORM_WIDTH: f32 = 2.0;
+const PLATFORM_HEIGHT: f32 = 0.5;
+const PLATFORM_PERIOD: f32 = 4.0;
+const RIDER_TOLERANCE: f32 = 0.75;
+const GAP_PROBABILITY: f64 = 0.04;
+const GAP_MARGIN: usize = 10;
+const MIN_GAP_WIDTH: i32 = 3;
+const MAX_GAP_WIDTH: i32 = 5;
+const MIN_GAP_DISTANCE: i32 = 12;
+const ELEVATOR_MIN_HEIGHT_DIFFERENCE: usize = 2;
+const MIN_CIRCLE_RADIUS: f32 = 1.0;
+
+pub struct PlatformsPlugin;
+
+impl Plugin for PlatformsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(AppState::InGame).with_system(move_platforms));
+    }
+}
+
+// Horizontal and vertical platforms go back and forth from their origin, circular ones
+// go around it. Every path takes `period` seconds.
+#[derive(Deserialize, Copy, Clone)]
+pub enum PlatformPath {
+    Horizontal { distance: f32, period: f32 },
+    Vertical { distance: f32, period: f32 },
+    Circular { radius: f32, period: f32 },
+}
+
+impl PlatformPath {
+    fn offset(&self, time: f32) -> Vec2 {
+        match *self {
+            PlatformPath::Horizontal { distance, period } => {
+                Vec2::new(back_and_forth(distance, period, time), 0.)
+            }
+            PlatformPath::Vertical { distance, period } => {
+                Vec2::new(0., back_and_forth(distance, period, time))
+            }
+            PlatformPath::Circular { radius, period } => {
+                let angle = 2. * PI * time / period;
+                Vec2::new(angle.cos(), angle.sin()) * radius
+            }
+        }
+    }
+}
+
+fn back_and_forth(distance: f32, period: f32, time: f32) -> f32 {
+    distance * (1. - (2. * PI * time / period).cos()) / 2.
+}
+
+// Platform placed by hand in a level file, `height` is counted from the ground at `x`
+#[derive(Deserialize)]
+pub struct PlatformSpec {
+    pub x: i32,
+    pub height: f32,
+    pub path: PlatformPath,
+}
+
+#[derive(Component)]
+pub struct Platform {
+    origin: Vec2,
+    path: PlatformPath,
+    time: f32,
+}
+
+// Columns from `start` to `end` (exclusive) removed from the map
+pub struct Gap {
+    pub start: i32,
+    pub end: i32,
+    pub left_height: usize,
+    pub right_height: usize,
+}
+
+pub fn spawn_platform(
+    commands: &mut Commands,
+    game_textures: &Res<GameTextures>,
+    x: f32,
+    y: f32,
+    path: PlatformPath,
+) {
+    let origin = Vec2::new(x, y);
+    let start = origin + path.offset(0.);
+    let platform = commands
+        .spawn_bundle(create_sprite_bundle(
+            game_textures.platform.clone(),
+            (PLATFORM_WIDTH, PLATFORM_HEIGHT),
+            (start.x, start.y, 0.),
+        ))
+        .insert(RigidBody::KinematicPositionBased)
+        .id();
+    spawn_solid_collider(
+        commands,
+        platform,
+        Collider::cuboid(PLATFORM_WIDTH / 2., PLATFORM_HEIGHT / 2.),
+        None,
+    );
+    commands
+        .entity(platform)
+        .insert(Platform {
+            origin,
+            path,
+            time: 0.,
+        })
+        .insert(Wall);
+}
+
+pub fn carve_gaps(world: &mut Vec<(i32, usize)>, rng: &mut Random, level: &Res<Level>) -> Vec<Gap> {
+    let probability = (GAP_PROBABILITY * level.factors.terrain_roughness).min(1.);
+    let height_at = |x: i32| {
+        world
+            .iter()
+            .find(|&&(column, _)| column == x)
+            .map_or(0, |&(_, height)| height)
+    };
+
+    // gaps need a column on both sides, and stay away from the start and the finish
+    let (first, last) = match (world.first(), world.last()) {
+        (Some(&(first, _)), Some(&(last, _))) => (first, last),
+        _ => return Vec::new(),
+    };
+    let end = (level.finish_x() - GAP_MARGIN as i32).min(last) - MAX_GAP_WIDTH;
+
+    let mut gaps = Vec::new();
+    let mut x = ((SAFE_ZONE_WIDTH + GAP_MARGIN) as i32).max(first + 1);
+    while x < end {
+        if rng.generator.gen_bool(probability) {
+            let width = rng.generator.gen_range(MIN_GAP_WIDTH..=MAX_GAP_WIDTH);
+            gaps.push(Gap {
+                start: x,
+                end: x + width,
+                left_height: height_at(x - 1),
+                right_height: height_at(x + width),
+            });
+            x += width + MIN_GAP_DISTANCE;
+        } else {
+            x += 1;
+        }
+    }
+
+    world.retain(|&(x, _)| !gaps.iter().any(|gap| gap.start <= x && x < gap.end));
+    gaps
+}
+
+// Every gap gets a platform, so that it can always be crossed. Gaps between columns of very
+// different heights get an elevator.
+pub fn add_gap_platforms(
+    commands: &mut Commands,
+    gaps: &[Gap],
+    game_textures: &Res<GameTextures>,
+    rng: &mut Random,
+) {
+    for gap in gaps {
+        let (left, right) = (gap.start as f32 - 0.5, gap.end as f32 - 0.5);
+        let (low, high) = (
+            gap.left_height.min(gap.right_height),
+            gap.left_height.max(gap.right_height),
+        );
+        // platforms are flush with the surface of the column
+        let surface = |height: usize| height as f32 + 0.5 - PLATFORM_HEIGHT / 2.;
+        let period = rng.jitter(PLATFORM_PERIOD);
+
+        let (x, y, path) = if high - low >= ELEVATOR_MIN_HEIGHT_DIFFERENCE {
+            let distance = (high - low) as f32;
+            (
+                (left + right) / 2.,
+                surface(low),
+                PlatformPath::Vertical { distance, period },
+            )
+        } else if rng.generator.gen_bool(0.5) {
+            let distance = right - left - PLATFORM_WIDTH;
+            let path = PlatformPath::Horizontal { distance, period };
+            (left + PLATFORM_WIDTH / 2., surface(high), path)
+        } else {
+            let radius = ((right - left - PLATFORM_WIDTH) / 2.).max(MIN_CIRCLE_RADIUS);
+            let path = PlatformPath::Circular { radius, period };
+            ((left + right) / 2., surface(high), path)
+        };
+        spawn_platform(commands, game_textures, x, y, path);
+    }
+}
+
+pub fn add_level_platforms(
+    commands: &mut Commands,
+    world: &[(i32, usize)],
+    platforms: &[PlatformSpec],
+    game_textures: &Res<GameTextures>,
+) {
+    for spec in platforms {
+        let ground = world
+            .iter()
+            .find(|&&(x, _)| x == spec.x)
+            .map_or(0., |&(_, height)| height as f32 + 0.5);
+        spawn_platform(
+            commands,
+            game_textures,
+            spec.x as f32,
+            ground + spec.height,
+            spec.path,
+        );
+    }
+}
+
+// Riders are moved together with the platform, so that they don't slide off it
+#[allow(clippy::type_complexity)]
+fn move_platforms(
+    time: Res<Time>,
+    mut platforms: Query<(&mut Platform, &mut Transform)>,
+    mut riders: Query<&mut Transform, (Or<(With<Player>, With<Enemy>)>, Without<Platform>)>,
+) {
+    for (mut platform, mut transform) in platforms.iter_mut() {
+        platform.time += time.delta_seconds();
+        let position = platform.origin + platform.path.offset(platform.time);
+        let delta = position - transform.translation.truncate();
+        let top = transform.translation.y + PLATFORM_HEIGHT / 2.;
+
+        for mut rider in riders.iter_mut() {
+            let above = rider.translation.y - top;
+            if (rider.translation.x - transform.translation.x).abs() < PLATFORM_WIDTH / 2.
+                && above > 0.
+                && above < RIDER_TOLERANCE
+            {
+                rider.translation += delta.extend(0.);
+            }
+        }
+
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
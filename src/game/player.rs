@@ -6,9 +6,10 @@ use bevy_rapier2d::prelude::*;
 use crate::game::bullets::{spawn_strong_bullet, spawn_weak_bullet, BulletOptions};
 use crate::game::monster::death_by_enemy;
 use crate::game::{
-    camera_follow_player, AudioDeadPlayerEvent, AudioFastShootEvent, AudioShootEvent, Bullet,
-    FinishLine, GameDirection, LastDespawnedEntity, PhantomEntity, Weapon, COFFEE_DURATION,
-    RUST_DURATION,
+    camera_follow_player, fit_camera_to_window, Animator, Bullet, CameraController,
+    CameraEffectEvent, CameraEffects, Clip, Dying, EndlessRun, FinishLine, GameDirection, Hazard,
+    LastDespawnedEntity, LevelBounds, ParticleEffect, ParticleEvent, PhantomEntity, PlaySfx,
+    Upgrades, Weapon, RUST_DURATION,
 };
 use crate::GameTextures;
 
@@ -21,35 +22,44 @@ pub struct PlayerPlugin;
 
 pub struct DeadPlayerEvent;
 
-const PLAYER_NORMAL_SPEED: f32 = 8.0;
-const PLAYER_INCREASE_SPEED: f32 = 10.0;
+pub const PLAYER_NORMAL_SPEED: f32 = 8.0;
+const PLAYER_COFFEE_SPEED_BONUS: f32 = 2.0;
+// the start is on the ground at height 0, respawns are as high above their own ground
+const PLAYER_SPAWN_POSITION: (f32, f32) = (0.0, 2.0);
+// hazards this close to a column make it unsafe to respawn on
+const HAZARD_CLEARANCE: f32 = 1.0;
 
 #[derive(Component)]
 pub struct Player {
     pub speed: f32,
+    pub base_speed: f32,
     pub weapon: Weapon,
+    pub base_weapon: Weapon,
     pub direction: GameDirection,
     pub weapon_upgrade_timer: Timer,
     pub coffee_timer: Timer,
+    pub coffee_duration: u64,
 }
 
-impl Default for Player {
-    fn default() -> Self {
+impl Player {
+    pub fn new(upgrades: &Upgrades) -> Self {
         Player {
-            speed: PLAYER_NORMAL_SPEED,
-            weapon: Weapon::WeakBullet,
+            speed: upgrades.player_speed(),
+            base_speed: upgrades.player_speed(),
+            weapon: upgrades.starting_weapon(),
+            base_weapon: upgrades.starting_weapon(),
             direction: GameDirection::Right,
             weapon_upgrade_timer: Timer::new(Duration::from_secs(0), false),
             coffee_timer: Timer::new(Duration::from_secs(0), false),
+            coffee_duration: upgrades.coffee_duration(),
         }
     }
-}
 
-impl Player {
-    pub fn spawn(commands: &mut Commands, game_textures: Res<GameTextures>) {
+    pub fn spawn(commands: &mut Commands, game_textures: Res<GameTextures>, upgrades: &Upgrades) {
+        let (x, y) = PLAYER_SPAWN_POSITION;
         let mut player_entity = spawn_dynamic_object(
             commands,
-            create_sprite_bundle(game_textures.player.clone(), (0.9, 0.9), (0.0, 2.0, 0.0)),
+            create_sprite_sheet_bundle(game_textures.player.clone(), (0.9, 0.9), (x, y, 0.0)),
             None,
             None,
         );
@@ -61,21 +71,22 @@ impl Player {
         );
         commands
             .entity(player_entity)
-            .insert(Player::default())
-            .insert(Jumper::default());
+            .insert(Player::new(upgrades))
+            .insert(Jumper::default())
+            .insert(Animator::default());
     }
 
     pub fn increase_speed(&mut self) {
-        self.coffee_timer = Timer::new(Duration::from_secs(COFFEE_DURATION), false);
-        self.speed = PLAYER_INCREASE_SPEED;
+        self.coffee_timer = Timer::new(Duration::from_secs(self.coffee_duration), false);
+        self.speed = self.base_speed + PLAYER_COFFEE_SPEED_BONUS;
     }
 
     pub fn decrease_speed(&mut self) {
-        self.speed = PLAYER_NORMAL_SPEED;
+        self.speed = self.base_speed;
     }
 
     pub fn degrade_weapon(&mut self) {
-        self.weapon = Weapon::WeakBullet;
+        self.weapon = self.base_weapon;
     }
 
     pub fn upgrade_weapon(&mut self) {
@@ -95,8 +106,10 @@ impl Plugin for PlayerPlugin {
                     .with_system(finish)
                     .with_system(death_by_enemy)
                     .with_system(camera_follow_player)
+                    .with_system(fit_camera_to_window)
                     .with_system(fire_controller)
-                    .with_system(handle_death),
+                    .with_system(handle_death)
+                    .with_system(fail_after_dying),
             )
             .add_event::<DeadPlayerEvent>();
     }
@@ -105,18 +118,23 @@ impl Plugin for PlayerPlugin {
 pub fn spawn_player(
     mut commands: Commands,
     game_textures: Res<GameTextures>,
+    upgrades: Res<Upgrades>,
     phantom_entity: Query<Entity, With<PhantomEntity>>,
 ) {
-    Player::spawn(&mut commands, game_textures);
-    commands.spawn_bundle(new_camera_2d());
+    Player::spawn(&mut commands, game_textures, &upgrades);
+    commands
+        .spawn_bundle(new_camera_2d())
+        .insert(CameraController::default())
+        .insert(CameraEffects::default());
     for entity in phantom_entity.iter() {
         commands.insert_resource(LastDespawnedEntity { entity });
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn player_jumps(
     keyboard_input: Res<Input<KeyCode>>,
-    mut players: Query<(&mut Jumper, &mut Velocity), With<Player>>,
+    mut players: Query<(&mut Jumper, &mut Velocity), (With<Player>, Without<Dying>)>,
 ) {
     if let Ok((mut jumper, mut velocity)) = players.get_single_mut() {
         if keyboard_input.pressed(KeyCode::Up) && !jumper.is_jumping {
@@ -128,7 +146,7 @@ pub fn player_jumps(
 
 pub fn player_movement(
     keyboard_input: Res<Input<KeyCode>>,
-    mut players: Query<(&mut Player, &mut Velocity)>,
+    mut players: Query<(&mut Player, &mut Velocity), Without<Dying>>,
 ) {
     if let Ok((mut player, mut velocity)) = players.get_single_mut() {
         if keyboard_input.pressed(KeyCode::Left) {
@@ -142,29 +160,37 @@ pub fn player_movement(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn fire_controller(
     keyboard_input: Res<Input<KeyCode>>,
     mut commands: Commands,
     game_textures: Res<GameTextures>,
-    positions: Query<(&mut Transform, &RigidBody, &mut Player, &mut Velocity), With<Player>>,
-    mut send_shoot_event: EventWriter<AudioShootEvent>,
-    mut send_fast_shoot_event: EventWriter<AudioFastShootEvent>,
+    mut positions: Query<(&Transform, &Player, &Velocity, &mut Animator), Without<Dying>>,
+    mut send_sfx: EventWriter<PlaySfx>,
+    mut send_camera_effect_event: EventWriter<CameraEffectEvent>,
+    mut send_particle_event: EventWriter<ParticleEvent>,
 ) {
     if keyboard_input.just_pressed(KeyCode::Space) {
-        for (pos, _, player, vel) in positions.iter() {
+        for (pos, player, vel, mut animator) in positions.iter_mut() {
+            animator.play(Clip::Shoot);
             let options = BulletOptions {
                 x: pos.translation.x,
                 y: pos.translation.y,
                 direction: player.direction,
                 player_vex: vel.linvel.x,
             };
+            send_particle_event.send(ParticleEvent::new(
+                ParticleEffect::MuzzleFlash(player.direction),
+                options.muzzle(),
+            ));
             match player.weapon {
                 Weapon::WeakBullet => {
-                    send_shoot_event.send(AudioShootEvent);
+                    send_sfx.send(PlaySfx::at("shoot", options.muzzle()));
                     spawn_weak_bullet(&mut commands, &game_textures, options);
                 }
                 Weapon::StrongBullet => {
-                    send_fast_shoot_event.send(AudioFastShootEvent);
+                    send_sfx.send(PlaySfx::at("fast_shoot", options.muzzle()));
+                    send_camera_effect_event.send(CameraEffectEvent::StrongShot);
                     spawn_strong_bullet(&mut commands, &game_textures, options);
                 }
             }
@@ -193,17 +219,73 @@ pub fn jump_reset(
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn handle_death(
-    mut state: ResMut<State<AppState>>,
+    mut commands: Commands,
+    mut upgrades: ResMut<Upgrades>,
+    mut players: Query<
+        (Entity, &mut Transform, &mut Velocity, &mut Animator),
+        (With<Player>, Without<Dying>),
+    >,
+    hazards: Query<&Transform, (With<Hazard>, Without<Player>)>,
+    run: Option<Res<EndlessRun>>,
+    bounds: Option<Res<LevelBounds>>,
     mut dead_player_events: EventReader<DeadPlayerEvent>,
-    mut event_senders: EventWriter<AudioDeadPlayerEvent>,
+    mut send_sfx: EventWriter<PlaySfx>,
+    mut send_camera_effect_event: EventWriter<CameraEffectEvent>,
+) {
+    // the player can be hit by several things at once, but loses only one life
+    if dead_player_events.iter().count() == 0 {
+        return;
+    }
+    // nothing hurts a player that is already dying
+    let (entity, mut transform, mut velocity, mut animator) = match players.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    send_sfx.send(PlaySfx::new("death"));
+    send_camera_effect_event.send(CameraEffectEvent::PlayerDeath);
+    *velocity = Velocity::zero();
+    if upgrades.extra_lives > 0 {
+        upgrades.extra_lives -= 1;
+        // the start of an endless run is long gone, the player comes back near where they died
+        let (x, y) = PLAYER_SPAWN_POSITION;
+        let respawn = match (run, bounds) {
+            (Some(run), Some(bounds)) => run
+                .respawn_column(transform.translation.x, bounds.left, |column| {
+                    hazards.iter().all(|hazard| {
+                        (hazard.translation.x - column as f32).abs() > HAZARD_CLEARANCE
+                    })
+                })
+                .map(|(column, height)| (column as f32, height as f32 + y)),
+            _ => None,
+        };
+        let (x, y) = respawn.unwrap_or((x, y));
+        transform.translation = Vec3::new(x, y, transform.translation.z);
+        animator.play(Clip::Hurt);
+    } else {
+        // the player stays where they died until the fail menu shows up
+        animator.play(Clip::Die);
+        commands
+            .entity(entity)
+            .insert(RigidBody::Fixed)
+            .insert(Dying);
+    }
+}
+
+fn fail_after_dying(
+    mut state: ResMut<State<AppState>>,
+    players: Query<&Animator, (With<Player>, With<Dying>)>,
 ) {
-    dead_player_events.iter().for_each(|_| {
+    if players
+        .get_single()
+        .is_ok_and(|animator| animator.finished())
+    {
         state
             .set(AppState::FailMenu)
             .expect("Could not set state to DeathMenu");
-        event_senders.send(AudioDeadPlayerEvent);
-    });
+    }
 }
 
 pub fn finish(
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use rand::Rng;
 
-use crate::game::{AudioCoffeeEvent, AudioRustEvent, Coffee, Player, Powerup, Rust};
+use crate::game::{Coffee, ParticleEffect, ParticleEvent, PlaySfx, Player, Powerup, Rust};
 use crate::{GameTextures, Level, Random};
 
 use super::utils::*;
@@ -60,11 +60,11 @@ fn spawn_powerup<T>(
         .insert(powerup_type);
 }
 
-fn spawn_coffee(commands: &mut Commands, game_textures: &Res<GameTextures>, x: f32, y: f32) {
+pub fn spawn_coffee(commands: &mut Commands, game_textures: &Res<GameTextures>, x: f32, y: f32) {
     spawn_powerup(commands, game_textures.coffee.clone(), Coffee, x, y);
 }
 
-fn spawn_rust(commands: &mut Commands, game_textures: &Res<GameTextures>, x: f32, y: f32) {
+pub fn spawn_rust(commands: &mut Commands, game_textures: &Res<GameTextures>, x: f32, y: f32) {
     spawn_powerup(commands, game_textures.rust.clone(), Rust, x, y);
 }
 
@@ -95,13 +95,21 @@ fn handle_coffee_event(
     mut commands: Commands,
     mut coffee_events: EventReader<CoffeeEvent>,
     mut players: Query<&mut Player>,
-    mut send_event: EventWriter<AudioCoffeeEvent>,
+    transforms: Query<&Transform, With<Coffee>>,
+    mut send_sfx: EventWriter<PlaySfx>,
+    mut send_particle_event: EventWriter<ParticleEvent>,
 ) {
     if let Ok(mut player) = players.get_single_mut() {
         coffee_events.iter().for_each(|coffee_event| {
             player.increase_speed();
+            if let Ok(transform) = transforms.get(coffee_event.coffee) {
+                send_sfx.send(PlaySfx::at("drink", transform.translation));
+                send_particle_event.send(ParticleEvent::new(
+                    ParticleEffect::PickupSparkle,
+                    transform.translation,
+                ));
+            }
             commands.entity(coffee_event.coffee).despawn_recursive();
-            send_event.send(AudioCoffeeEvent);
         });
     }
 }
@@ -142,13 +150,21 @@ fn handle_rust_event(
     mut commands: Commands,
     mut rust_events: EventReader<RustEvent>,
     mut players: Query<&mut Player>,
-    mut send_event: EventWriter<AudioRustEvent>,
+    transforms: Query<&Transform, With<Rust>>,
+    mut send_sfx: EventWriter<PlaySfx>,
+    mut send_particle_event: EventWriter<ParticleEvent>,
 ) {
     if let Ok(mut player) = players.get_single_mut() {
         rust_events.iter().for_each(|rust_event| {
             player.upgrade_weapon();
+            if let Ok(transform) = transforms.get(rust_event.rust) {
+                send_sfx.send(PlaySfx::at("level_up", transform.translation));
+                send_particle_event.send(ParticleEvent::new(
+                    ParticleEffect::PickupSparkle,
+                    transform.translation,
+                ));
+            }
             commands.entity(rust_event.rust).despawn_recursive();
-            send_event.send(AudioRustEvent);
         });
     }
 }
@@ -165,32 +181,32 @@ pub fn finish_rust(mut players: Query<&mut Player>, time: Res<Time>) {
 pub fn add_powerups(
     commands: &mut Commands,
     world: &[(i32, usize)],
-    game_textures: Res<GameTextures>,
-    rng: &mut ResMut<Random>,
+    game_textures: &Res<GameTextures>,
+    rng: &mut Random,
     level: &Res<Level>,
 ) {
     world.iter().for_each(|&(x, height)| {
         if should_add_coffee(x, rng, level) {
-            spawn_coffee(commands, &game_textures, x as f32, height as f32 + 0.75);
+            spawn_coffee(commands, game_textures, x as f32, height as f32 + 0.75);
         }
         if should_add_rust(x, rng, level) {
-            spawn_rust(commands, &game_textures, x as f32, height as f32 + 0.75);
+            spawn_rust(commands, game_textures, x as f32, height as f32 + 0.75);
         }
     });
 }
 
-fn should_add_coffee(x: i32, rng: &mut ResMut<Random>, level: &Res<Level>) -> bool {
+fn should_add_coffee(x: i32, rng: &mut Random, level: &Res<Level>) -> bool {
     if x <= SAFE_ZONE_WIDTH {
         return false;
     }
     rng.generator
-        .gen_bool(SPAWNING_COFFEE_PROBABILITY / level.difficulty)
+        .gen_bool((SPAWNING_COFFEE_PROBABILITY * level.factors.powerup_frequency).min(1.))
 }
 
-fn should_add_rust(x: i32, rng: &mut ResMut<Random>, level: &Res<Level>) -> bool {
+fn should_add_rust(x: i32, rng: &mut Random, level: &Res<Level>) -> bool {
     if x <= SAFE_ZONE_WIDTH {
         return false;
     }
     rng.generator
-        .gen_bool(SPAWNING_RUST_PROBABILITY / level.difficulty)
+        .gen_bool((SPAWNING_RUST_PROBABILITY * level.factors.powerup_frequency).min(1.))
 }
@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::game::{
+    spawn_enemy, EnemyArchetypes, EnemyAssets, PlatformSpec, Player, RonAssetPlugin, GAME_WIDTH,
+    SAFE_ZONE_WIDTH,
+};
+use crate::{AppState, Level, Random};
+
+const PROGRESSION_PATH: &str = "data/default.progression.ron";
+const MINI_BOSS_DISTANCE_FROM_FINISH: usize = 8;
+const MINI_BOSS_SPAWN_HEIGHT: f32 = 2.5;
+const AMBUSH_MARGIN: usize = 20;
+const AMBUSH_DISTANCE: f32 = 3.0;
+const AMBUSH_SPACING: f32 = 1.5;
+const AMBUSH_DROP_HEIGHT: f32 = 4.0;
+
+pub struct ProgressionPlugin;
+
+impl Plugin for ProgressionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RonAssetPlugin::<LevelProgression>::new("progression.ron"))
+            .add_startup_system(load_progression)
+            .add_system_set(SystemSet::on_update(AppState::InGame).with_system(trigger_ambushes));
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct EnemySpawn {
+    pub archetype: String,
+    // chance of spawning the enemy on a column, before the difficulty is applied
+    pub density: f64,
+}
+
+#[derive(Deserialize)]
+pub enum Encounter {
+    // a tougher enemy guarding the finish line
+    MiniBoss { archetype: String },
+    // a group of enemies dropping around the player once they reach the ambush
+    Ambush { archetype: String, count: usize },
+}
+
+// Enemies and encounters for levels from `from_level` to `to_level`
+#[derive(Deserialize)]
+pub struct LevelBand {
+    pub from_level: usize,
+    pub to_level: Option<usize>,
+    pub enemies: Vec<EnemySpawn>,
+    pub encounters: Vec<Encounter>,
+    #[serde(default)]
+    pub platforms: Vec<PlatformSpec>,
+    // names of the biomes the levels can take place in, any biome if empty
+    #[serde(default)]
+    pub biomes: Vec<String>,
+}
+
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "5c0b4a9e-2f7d-4d0e-9b63-1f3e8a6c2d71"]
+pub struct LevelProgression {
+    pub bands: Vec<LevelBand>,
+}
+
+impl LevelProgression {
+    pub fn band(&self, level: usize) -> Option<&LevelBand> {
+        self.bands.iter().find(|band| {
+            band.from_level <= level && band.to_level.is_none_or(|to_level| level <= to_level)
+        })
+    }
+}
+
+pub struct ProgressionAssets {
+    pub progression: Handle<LevelProgression>,
+}
+
+#[derive(Component)]
+pub struct Ambush {
+    pub x: f32,
+    pub archetype: String,
+    // where the enemies appear, above the ground of their columns
+    pub drops: Vec<Vec2>,
+}
+
+fn load_progression(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ProgressionAssets {
+        progression: asset_server.load(PROGRESSION_PATH),
+    });
+}
+
+pub fn add_encounters(
+    commands: &mut Commands,
+    world: &[(i32, usize)],
+    enemy_assets: &EnemyAssets,
+    archetypes: &EnemyArchetypes,
+    band: &LevelBand,
+    rng: &mut Random,
+    level: &Res<Level>,
+) {
+    for encounter in band.encounters.iter() {
+        match encounter {
+            Encounter::MiniBoss { archetype } => {
+                if let (Some(archetype), Some(&(x, height))) = (
+                    archetypes.get(archetype),
+                    world.iter().rev().nth(MINI_BOSS_DISTANCE_FROM_FINISH),
+                ) {
+                    let y = height as f32 + MINI_BOSS_SPAWN_HEIGHT;
+                    spawn_enemy(
+                        commands,
+                        enemy_assets,
+                        archetype,
+                        &level.factors,
+                        rng,
+                        x as f32,
+                        y,
+                    );
+                }
+            }
+            Encounter::Ambush { archetype, count } => {
+                let x = rng
+                    .generator
+                    .gen_range(SAFE_ZONE_WIDTH + AMBUSH_MARGIN..GAME_WIDTH - AMBUSH_MARGIN);
+                // enemies drop on both sides of the player, but not into gaps
+                let drops = (0..*count)
+                    .filter_map(|i| {
+                        let offset = AMBUSH_DISTANCE + (i / 2) as f32 * AMBUSH_SPACING;
+                        let drop_x = if i % 2 == 0 {
+                            x as f32 + offset
+                        } else {
+                            x as f32 - offset
+                        };
+                        let column = drop_x.round() as i32;
+                        world
+                            .iter()
+                            .find(|&&(x, _)| x == column)
+                            .map(|&(_, height)| {
+                                Vec2::new(drop_x, height as f32 + AMBUSH_DROP_HEIGHT)
+                            })
+                    })
+                    .collect();
+                commands.spawn().insert(Ambush {
+                    x: x as f32,
+                    archetype: archetype.clone(),
+                    drops,
+                });
+            }
+        }
+    }
+}
+
+fn trigger_ambushes(
+    mut commands: Commands,
+    mut rng: ResMut<Random>,
+    level: Res<Level>,
+    enemy_assets: Res<EnemyAssets>,
+    archetypes: Res<Assets<EnemyArchetypes>>,
+    players: Query<&Transform, With<Player>>,
+    ambushes: Query<(Entity, &Ambush)>,
+) {
+    let player = match players.get_single() {
+        Ok(player) => player.translation.x,
+        Err(_) => return,
+    };
+
+    for (entity, ambush) in ambushes.iter() {
+        if player < ambush.x {
+            continue;
+        }
+        commands.entity(entity).despawn();
+
+        let archetype = match archetypes
+            .get(&enemy_assets.archetypes)
+            .and_then(|archetypes| archetypes.get(&ambush.archetype))
+        {
+            Some(archetype) => archetype,
+            None => continue,
+        };
+
+        for drop in ambush.drops.iter() {
+            spawn_enemy(
+                &mut commands,
+                &enemy_assets,
+                archetype,
+                &level.factors,
+                &mut rng,
+                drop.x,
+                drop.y,
+            );
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::marker::PhantomData;
+
+use bevy::asset::{Asset, AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+
+// Registers T as an asset read from RON files ending with the given extension,
+// e.g. "enemies.ron" for "data/default.enemies.ron"
+pub struct RonAssetPlugin<T> {
+    extensions: [&'static str; 1],
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> RonAssetPlugin<T> {
+    pub fn new(extension: &'static str) -> Self {
+        RonAssetPlugin {
+            extensions: [extension],
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Plugin for RonAssetPlugin<T>
+where
+    T: Asset + DeserializeOwned,
+{
+    fn build(&self, app: &mut App) {
+        app.add_asset::<T>().add_asset_loader(RonLoader::<T> {
+            extensions: self.extensions,
+            _marker: PhantomData,
+        });
+    }
+}
+
+struct RonLoader<T> {
+    extensions: [&'static str; 1],
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> AssetLoader for RonLoader<T>
+where
+    T: Asset + DeserializeOwned,
+{
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let asset = ron::de::from_bytes::<T>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+}
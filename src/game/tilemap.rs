@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy::sprite::MaterialMesh2dBundle;
+
+// Each chunk of the terrain is drawn as a single mesh
+pub const CHUNK_WIDTH: i32 = 16;
+// Tilesets have a row of tiles with a top edge and a row without it. The columns are for
+// tiles with an edge on the left, no side edges, an edge on the right and edges on both sides.
+const TILESET_COLUMNS: usize = 4;
+const TILESET_ROWS: usize = 2;
+const TILE_PIXELS: f32 = 16.0;
+const TILE_Z: f32 = 0.0;
+
+// The solid tiles of the level, as the lowest and the highest tile of each column
+#[derive(Default)]
+pub struct Terrain {
+    columns: BTreeMap<i32, (i32, i32)>,
+}
+
+impl Terrain {
+    pub fn from_world(world: &[(i32, usize)]) -> Self {
+        let mut terrain = Terrain::default();
+        for &(x, height) in world {
+            terrain.set_column(x, 0, height as i32);
+        }
+        terrain
+    }
+
+    pub fn set_column(&mut self, x: i32, bottom: i32, top: i32) {
+        self.columns.insert(x, (bottom, top));
+    }
+
+    pub fn height(&self, x: i32) -> Option<i32> {
+        self.columns.get(&x).map(|&(_, top)| top)
+    }
+
+    pub fn remove_before(&mut self, x: i32) {
+        self.columns = self.columns.split_off(&x);
+    }
+
+    fn is_solid(&self, x: i32, y: i32) -> bool {
+        self.columns
+            .get(&x)
+            .is_some_and(|&(bottom, top)| (bottom..=top).contains(&y))
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = i32> + '_ {
+        let mut previous = None;
+        self.columns.keys().filter_map(move |&x| {
+            let chunk = x.div_euclid(CHUNK_WIDTH);
+            (previous.replace(chunk) != Some(chunk)).then_some(chunk)
+        })
+    }
+
+    pub fn spawn_chunks(
+        &self,
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        material: &Handle<ColorMaterial>,
+    ) {
+        for chunk in self.chunks() {
+            self.spawn_chunk(commands, meshes, material, chunk);
+        }
+    }
+
+    pub fn spawn_chunk(
+        &self,
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        material: &Handle<ColorMaterial>,
+        chunk: i32,
+    ) -> Entity {
+        commands
+            .spawn_bundle(MaterialMesh2dBundle {
+                mesh: meshes.add(self.chunk_mesh(chunk)).into(),
+                material: material.clone(),
+                transform: Transform::from_xyz(0., 0., TILE_Z),
+                ..default()
+            })
+            .id()
+    }
+
+    fn chunk_mesh(&self, chunk: i32) -> Mesh {
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::new();
+
+        let first = chunk * CHUNK_WIDTH;
+        for (&x, &(bottom, top)) in self.columns.range(first..first + CHUNK_WIDTH) {
+            for y in bottom..=top {
+                let start = positions.len() as u32;
+                let (left, lower) = (x as f32 - 0.5, y as f32 - 0.5);
+                positions.extend([
+                    [left, lower, 0.],
+                    [left + 1., lower, 0.],
+                    [left + 1., lower + 1., 0.],
+                    [left, lower + 1., 0.],
+                ]);
+                indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+                uvs.extend(self.tile_uvs(x, y));
+            }
+        }
+
+        let normals = vec![[0., 0., 1.]; positions.len()];
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+
+    // Picks the tile with the edges facing the empty neighbours
+    fn tile_uvs(&self, x: i32, y: i32) -> [[f32; 2]; 4] {
+        let row = match self.is_solid(x, y + 1) {
+            true => 1,
+            false => 0,
+        };
+        let column = match (self.is_solid(x - 1, y), self.is_solid(x + 1, y)) {
+            (false, true) => 0,
+            (true, true) => 1,
+            (true, false) => 2,
+            (false, false) => 3,
+        };
+
+        // half a pixel is cut off each side so the neighbouring tiles don't bleed in
+        let (width, height) = (1. / TILESET_COLUMNS as f32, 1. / TILESET_ROWS as f32);
+        let (inset_x, inset_y) = (width / TILE_PIXELS / 2., height / TILE_PIXELS / 2.);
+        let left = column as f32 * width + inset_x;
+        let right = (column + 1) as f32 * width - inset_x;
+        let top = row as f32 * height + inset_y;
+        let bottom = (row + 1) as f32 * height - inset_y;
+        [[left, bottom], [right, bottom], [right, top], [left, top]]
+    }
+}
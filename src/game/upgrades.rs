@@ -0,0 +1,95 @@
+use crate::game::{RunStats, Weapon, COFFEE_DURATION, PLAYER_NORMAL_SPEED};
+
+const EXTRA_LIFE_PRICE: usize = 10;
+const SPEED_PRICE: usize = 8;
+const COFFEE_PRICE: usize = 5;
+const STARTING_WEAPON_PRICE: usize = 20;
+const MAX_SPEED_LEVEL: usize = 3;
+const MAX_COFFEE_LEVEL: usize = 3;
+const SPEED_BONUS: f32 = 0.5;
+const COFFEE_BONUS: u64 = 3;
+
+#[derive(Copy, Clone)]
+pub enum Upgrade {
+    ExtraLife,
+    Speed,
+    CoffeeDuration,
+    StartingWeapon,
+}
+
+impl Upgrade {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Upgrade::ExtraLife => "Extra life",
+            Upgrade::Speed => "Faster legs",
+            Upgrade::CoffeeDuration => "Stronger coffee",
+            Upgrade::StartingWeapon => "Rust from the start",
+        }
+    }
+}
+
+// Upgrades bought in the shop, kept between levels until the run is restarted
+pub struct Upgrades {
+    pub extra_lives: usize,
+    pub speed_level: usize,
+    pub coffee_level: usize,
+    pub strong_starting_weapon: bool,
+}
+
+impl Upgrades {
+    pub fn new() -> Self {
+        Self {
+            extra_lives: 0,
+            speed_level: 0,
+            coffee_level: 0,
+            strong_starting_weapon: false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Upgrades::new();
+    }
+
+    // None means the upgrade can't be bought anymore
+    pub fn price(&self, upgrade: Upgrade) -> Option<usize> {
+        match upgrade {
+            Upgrade::ExtraLife => Some(EXTRA_LIFE_PRICE),
+            Upgrade::Speed if self.speed_level < MAX_SPEED_LEVEL => Some(SPEED_PRICE),
+            Upgrade::CoffeeDuration if self.coffee_level < MAX_COFFEE_LEVEL => Some(COFFEE_PRICE),
+            Upgrade::StartingWeapon if !self.strong_starting_weapon => Some(STARTING_WEAPON_PRICE),
+            _ => None,
+        }
+    }
+
+    pub fn buy(&mut self, upgrade: Upgrade, stats: &mut RunStats) -> bool {
+        match self.price(upgrade) {
+            Some(price) if price <= stats.ects => {
+                stats.ects -= price;
+                match upgrade {
+                    Upgrade::ExtraLife => self.extra_lives += 1,
+                    Upgrade::Speed => self.speed_level += 1,
+                    Upgrade::CoffeeDuration => self.coffee_level += 1,
+                    Upgrade::StartingWeapon => self.strong_starting_weapon = true,
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn player_speed(&self) -> f32 {
+        PLAYER_NORMAL_SPEED + self.speed_level as f32 * SPEED_BONUS
+    }
+
+    pub fn coffee_duration(&self) -> u64 {
+        COFFEE_DURATION + self.coffee_level as u64 * COFFEE_BONUS
+    }
+
+    pub fn starting_weapon(&self) -> Weapon {
+        if self.strong_starting_weapon {
+            Weapon::StrongBullet
+        } else {
+            Weapon::WeakBullet
+        }
+    }
+}
@@ -20,6 +20,22 @@ pub fn create_sprite_bundle(
     }
 }
 
+pub fn create_sprite_sheet_bundle(
+    texture_atlas: Handle<TextureAtlas>,
+    (x_size, y_size): (f32, f32),
+    (x_translation, y_translation, z_translation): (f32, f32, f32),
+) -> SpriteSheetBundle {
+    SpriteSheetBundle {
+        texture_atlas,
+        sprite: TextureAtlasSprite {
+            custom_size: Some(Vec2::new(x_size, y_size)),
+            ..default()
+        },
+        transform: Transform::from_xyz(x_translation, y_translation, z_translation),
+        ..default()
+    }
+}
+
 pub fn spawn_static_collider_object<T>(
     commands: &mut Commands,
     left_down: (f32, f32),
@@ -50,7 +66,7 @@ where
 
 pub fn spawn_dynamic_object(
     commands: &mut Commands,
-    sprite: SpriteBundle,
+    sprite: impl Bundle,
     x_velocity: Option<f32>,
     gravity_scale: Option<f32>,
 ) -> Entity {
@@ -1,45 +1,64 @@
 use bevy::prelude::*;
-use bevy::window::PresentMode;
 
 use game::GamePlugin;
 use menu::MenuPlugin;
+use settings::{Settings, SettingsPlugin};
 
 use crate::game::{
-    BulletsPlugin, GameAudioPlugin, GameTextures, Level, MapPlugin, MonsterAiPlugin, PlayerPlugin,
-    PowerupsPlugin, Random,
+    AnimationPlugin, ArchetypesPlugin, BiomesPlugin, BlocksPlugin, BulletsPlugin,
+    CameraEffectsPlugin, Difficulty, EctsPlugin, EndlessPlugin, GameAudioPlugin, GameMode,
+    GameTextures, HazardsPlugin, Level, LoadingPlugin, MapPlugin, MonsterAiPlugin, ParallaxPlugin,
+    ParticlesPlugin, PlatformsPlugin, PlaySfx, PlayerPlugin, PowerupsPlugin, ProgressionPlugin,
+    Random, RunStats, Upgrade, Upgrades,
 };
 
 mod game;
 mod menu;
+mod settings;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum AppState {
+    Loading,
     InGame,
     MainMenu,
     FailMenu,
     WinMenu,
+    ShopMenu,
     StopMenu,
+    SettingsMenu,
+    AudioMenu,
 }
 
 fn main() {
+    let (settings, load_error) = Settings::load();
     App::new()
+        // the window is created from the descriptor when the plugins are added
+        .insert_resource(settings.window_descriptor())
         .add_plugins(DefaultPlugins)
-        .insert_resource(WindowDescriptor {
-            title: "Mario MIM!".to_string(),
-            width: 640.0,
-            height: 400.0,
-            present_mode: PresentMode::Fifo,
-            ..default()
-        })
         .insert_resource(ClearColor(Color::BEIGE))
-        .add_state(AppState::MainMenu)
+        .insert_resource(settings)
+        .add_plugin(SettingsPlugin { load_error })
+        .add_state(AppState::Loading)
         .add_plugin(GamePlugin)
         .add_plugin(BulletsPlugin)
         .add_plugin(GameAudioPlugin)
         .add_plugin(PowerupsPlugin)
+        .add_plugin(EctsPlugin)
+        .add_plugin(HazardsPlugin)
+        .add_plugin(PlatformsPlugin)
+        .add_plugin(BlocksPlugin)
         .add_plugin(PlayerPlugin)
         .add_plugin(MapPlugin)
         .add_plugin(MenuPlugin)
+        .add_plugin(ArchetypesPlugin)
+        .add_plugin(ProgressionPlugin)
+        .add_plugin(BiomesPlugin)
+        .add_plugin(ParallaxPlugin)
+        .add_plugin(CameraEffectsPlugin)
+        .add_plugin(AnimationPlugin)
+        .add_plugin(ParticlesPlugin)
+        .add_plugin(EndlessPlugin)
         .add_plugin(MonsterAiPlugin)
+        .add_plugin(LoadingPlugin)
         .run();
 }
@@ -2,10 +2,16 @@ use bevy::prelude::*;
 
 use crate::menu::structs::{MenuButton, MenuColors, MenuTextures};
 use crate::menu::systems::{
-    button_press_system, button_system, input_button_system, read_input_system, text_update_system,
+    button_press_system, button_system, input_button_system, option_text_system, read_input_system,
+    text_update_system, volume_text_system,
 };
-use crate::menu::utils::{cleanup_menu, setup_level_end, setup_main};
-use crate::{AppState, Level, Random};
+use crate::menu::utils::{
+    cleanup_menu, difficulty_label, display_mode_label, mute_label, screen_effects_label,
+    setup_audio_list, setup_level_end, setup_list, setup_main, volume_label, vsync_label,
+    window_size_label,
+};
+use crate::settings::{AudioBus, Settings};
+use crate::{AppState, GameMode, Level, Random, RunStats, Upgrade, Upgrades};
 
 mod structs;
 mod systems;
@@ -24,6 +30,7 @@ impl Plugin for MenuPlugin {
                 SystemSet::on_update(AppState::MainMenu)
                     .with_system(input_button_system)
                     .with_system(text_update_system)
+                    .with_system(option_text_system)
                     .with_system(read_input_system),
             )
             .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(cleanup_menu))
@@ -32,7 +39,23 @@ impl Plugin for MenuPlugin {
             .add_system_set(SystemSet::on_enter(AppState::FailMenu).with_system(setup_fail_menu))
             .add_system_set(SystemSet::on_exit(AppState::FailMenu).with_system(cleanup_menu))
             .add_system_set(SystemSet::on_enter(AppState::WinMenu).with_system(setup_win_menu))
-            .add_system_set(SystemSet::on_exit(AppState::WinMenu).with_system(cleanup_menu));
+            .add_system_set(SystemSet::on_exit(AppState::WinMenu).with_system(cleanup_menu))
+            .add_system_set(SystemSet::on_enter(AppState::ShopMenu).with_system(setup_shop_menu))
+            .add_system_set(SystemSet::on_exit(AppState::ShopMenu).with_system(cleanup_menu))
+            .add_system_set(
+                SystemSet::on_enter(AppState::SettingsMenu).with_system(setup_settings_menu),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::SettingsMenu).with_system(option_text_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::SettingsMenu).with_system(cleanup_menu))
+            .add_system_set(SystemSet::on_enter(AppState::AudioMenu).with_system(setup_audio_menu))
+            .add_system_set(
+                SystemSet::on_update(AppState::AudioMenu)
+                    .with_system(option_text_system)
+                    .with_system(volume_text_system),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::AudioMenu).with_system(cleanup_menu));
     }
 }
 
@@ -45,6 +68,7 @@ fn setup_main_menu(
     commands: Commands,
     colors: Res<MenuColors>,
     textures: Res<MenuTextures>,
+    level: Res<Level>,
 ) {
     *random = Random::new();
     setup_main(
@@ -53,8 +77,11 @@ fn setup_main_menu(
         textures,
         "Mario MIM",
         vec![
-            ("New game", MenuButton::NewGame),
-            ("Quit", MenuButton::Quit),
+            ("New game".to_string(), MenuButton::NewGame),
+            ("Endless run".to_string(), MenuButton::Endless),
+            (difficulty_label(level.difficulty), MenuButton::Difficulty),
+            ("Settings".to_string(), MenuButton::Settings),
+            ("Quit".to_string(), MenuButton::Quit),
         ],
     );
 }
@@ -64,18 +91,27 @@ fn setup_fail_menu(
     colors: Res<MenuColors>,
     textures: Res<MenuTextures>,
     level: Res<Level>,
+    stats: Res<RunStats>,
 ) {
-    setup_level_end(
-        commands,
-        colors,
-        textures,
-        vec![
+    let buttons = match level.mode {
+        GameMode::Campaign => vec![
             ("Try again level", MenuButton::RestartLevel),
             ("Start from beginning", MenuButton::RestartGame),
             ("Go to main menu", MenuButton::MainMenu),
         ],
+        GameMode::Endless => vec![
+            ("Try again", MenuButton::RestartGame),
+            ("Go to main menu", MenuButton::MainMenu),
+        ],
+    };
+    setup_level_end(
+        commands,
+        colors,
+        textures,
+        buttons,
         Some(false),
         level.level,
+        &stats_text(&level, &stats),
     );
 }
 
@@ -84,36 +120,158 @@ fn setup_win_menu(
     colors: Res<MenuColors>,
     textures: Res<MenuTextures>,
     level: Res<Level>,
+    stats: Res<RunStats>,
 ) {
     setup_level_end(
         commands,
         colors,
         textures,
         vec![
-            ("Next level", MenuButton::NextLevel),
+            ("Go to shop", MenuButton::Shop),
             ("Go to main menu", MenuButton::MainMenu),
         ],
         Some(true),
         level.level,
+        &level_stats_text(&stats),
     );
 }
 
-fn setup_stop_menu(
+fn setup_shop_menu(
     commands: Commands,
     colors: Res<MenuColors>,
     textures: Res<MenuTextures>,
-    level: Res<Level>,
+    stats: Res<RunStats>,
+    upgrades: Res<Upgrades>,
 ) {
-    setup_level_end(
+    let mut buttons: Vec<(String, MenuButton)> = [
+        Upgrade::ExtraLife,
+        Upgrade::Speed,
+        Upgrade::CoffeeDuration,
+        Upgrade::StartingWeapon,
+    ]
+    .into_iter()
+    .map(|upgrade| {
+        let label = match upgrades.price(upgrade) {
+            Some(price) => format!("{}: {} ECTS", upgrade.name(), price),
+            None => format!("{}: sold out", upgrade.name()),
+        };
+        (label, MenuButton::Buy(upgrade))
+    })
+    .collect();
+    buttons.push(("Next level".to_string(), MenuButton::NextLevel));
+
+    setup_list(
         commands,
         colors,
         textures,
+        "Shop",
+        &format!(
+            "ECTS: {}   Extra lives: {}",
+            stats.ects, upgrades.extra_lives
+        ),
+        buttons,
+    );
+}
+
+fn setup_settings_menu(
+    commands: Commands,
+    colors: Res<MenuColors>,
+    textures: Res<MenuTextures>,
+    settings: Res<Settings>,
+) {
+    setup_list(
+        commands,
+        colors,
+        textures,
+        "Settings",
+        "VSync changes apply after a restart",
         vec![
+            (window_size_label(&settings), MenuButton::WindowSize),
+            (display_mode_label(&settings), MenuButton::DisplayMode),
+            (vsync_label(&settings), MenuButton::Vsync),
+            (screen_effects_label(&settings), MenuButton::ScreenEffects),
+            ("Audio".to_string(), MenuButton::Audio),
+            ("Back".to_string(), MenuButton::MainMenu),
+        ],
+    );
+}
+
+fn setup_audio_menu(
+    commands: Commands,
+    colors: Res<MenuColors>,
+    textures: Res<MenuTextures>,
+    settings: Res<Settings>,
+) {
+    let volumes = [
+        AudioBus::Master,
+        AudioBus::Music,
+        AudioBus::Sfx,
+        AudioBus::Ui,
+    ]
+    .into_iter()
+    .map(|bus| (volume_label(&settings, bus), bus))
+    .collect();
+
+    setup_audio_list(
+        commands,
+        colors,
+        textures,
+        "Audio",
+        "Music is turned down while the game is paused",
+        volumes,
+        vec![
+            (mute_label(&settings), MenuButton::Mute),
+            ("Back".to_string(), MenuButton::Settings),
+        ],
+    );
+}
+
+fn setup_stop_menu(
+    commands: Commands,
+    colors: Res<MenuColors>,
+    textures: Res<MenuTextures>,
+    level: Res<Level>,
+    stats: Res<RunStats>,
+) {
+    let buttons = match level.mode {
+        GameMode::Campaign => vec![
             ("Restart level", MenuButton::RestartLevel),
             ("Restart game", MenuButton::RestartGame),
             ("Go to main menu", MenuButton::MainMenu),
         ],
+        GameMode::Endless => vec![
+            ("Restart run", MenuButton::RestartGame),
+            ("Go to main menu", MenuButton::MainMenu),
+        ],
+    };
+    setup_level_end(
+        commands,
+        colors,
+        textures,
+        buttons,
         None,
         level.level,
+        &stats_text(&level, &stats),
     );
 }
+
+// Endless runs are scored by the distance reached
+fn stats_text(level: &Level, stats: &RunStats) -> String {
+    match level.mode {
+        GameMode::Campaign => banked_stats_text(stats),
+        GameMode::Endless => format!("ECTS: {}   Distance: {}", stats.level_ects, stats.distance),
+    }
+}
+
+fn banked_stats_text(stats: &RunStats) -> String {
+    format!("ECTS: {}   Score: {}", stats.ects, stats.score)
+}
+
+fn level_stats_text(stats: &RunStats) -> String {
+    format!(
+        "ECTS: {} (+{})   Score: {}",
+        stats.ects + stats.level_ects,
+        stats.level_ects,
+        stats.score + stats.level_score
+    )
+}
@@ -1,8 +1,12 @@
 use bevy::prelude::*;
 
+use crate::settings::AudioBus;
+use crate::Upgrade;
+
 #[derive(Component)]
 pub enum MenuButton {
     NewGame,
+    Endless,
     Quit,
     MainMenu,
     SeedGenerate,
@@ -10,8 +14,24 @@ pub enum MenuButton {
     NextLevel,
     RestartLevel,
     RestartGame,
+    Shop,
+    Buy(Upgrade),
+    Difficulty,
+    Settings,
+    ScreenEffects,
+    WindowSize,
+    DisplayMode,
+    Vsync,
+    Audio,
+    VolumeDown(AudioBus),
+    VolumeUp(AudioBus),
+    Mute,
 }
 
+// Text showing the volume of a bus, between the buttons changing it
+#[derive(Component)]
+pub struct VolumeText(pub AudioBus);
+
 pub struct MenuTextures {
     pub play: Handle<Image>,
     pub exit: Handle<Image>,
@@ -1,10 +1,15 @@
 use bevy::app::AppExit;
 use bevy::prelude::*;
 
-use crate::menu::structs::{InputText, MenuButton, MenuColors, SelectedOption};
-use crate::menu::utils::{start_game_for_level, start_new_game};
-use crate::{AppState, Level, Random};
+use crate::menu::structs::{InputText, MenuButton, MenuColors, SelectedOption, VolumeText};
+use crate::menu::utils::{
+    difficulty_label, display_mode_label, mute_label, screen_effects_label, start_game_for_level,
+    start_new_game, volume_label, vsync_label, window_size_label,
+};
+use crate::settings::Settings;
+use crate::{AppState, GameMode, Level, PlaySfx, Random, RunStats, Upgrades};
 
+#[allow(clippy::too_many_arguments)]
 pub fn button_press_system(
     mut commands: Commands,
     mut state: ResMut<State<AppState>>,
@@ -12,11 +17,33 @@ pub fn button_press_system(
     mut exit: EventWriter<AppExit>,
     mut rng: ResMut<Random>,
     mut level: ResMut<Level>,
+    mut stats: ResMut<RunStats>,
+    mut upgrades: ResMut<Upgrades>,
+    mut settings: ResMut<Settings>,
+    mut send_sfx: EventWriter<PlaySfx>,
 ) {
-    for (interaction, button, entity, _) in buttons.iter() {
+    for (interaction, button, entity, changed) in buttons.iter() {
         if *interaction == Interaction::Clicked {
+            if changed {
+                send_sfx.send(PlaySfx::new("click"));
+            }
             match button {
-                MenuButton::NewGame => start_new_game(&mut state, &mut rng, &mut level),
+                MenuButton::NewGame => start_new_game(
+                    &mut state,
+                    &mut rng,
+                    &mut level,
+                    &mut stats,
+                    &mut upgrades,
+                    GameMode::Campaign,
+                ),
+                MenuButton::Endless => start_new_game(
+                    &mut state,
+                    &mut rng,
+                    &mut level,
+                    &mut stats,
+                    &mut upgrades,
+                    GameMode::Endless,
+                ),
                 MenuButton::Quit => exit.send(AppExit),
                 MenuButton::MainMenu => state
                     .set(AppState::MainMenu)
@@ -32,7 +59,73 @@ pub fn button_press_system(
                     start_game_for_level(&mut state, &mut rng, &mut level);
                 }
                 MenuButton::RestartLevel => start_game_for_level(&mut state, &mut rng, &mut level),
-                MenuButton::RestartGame => start_new_game(&mut state, &mut rng, &mut level),
+                MenuButton::RestartGame => {
+                    let mode = level.mode;
+                    start_new_game(
+                        &mut state,
+                        &mut rng,
+                        &mut level,
+                        &mut stats,
+                        &mut upgrades,
+                        mode,
+                    )
+                }
+                MenuButton::Shop => state
+                    .set(AppState::ShopMenu)
+                    .expect("Couldn't switch state to ShopMenu"),
+                MenuButton::Buy(upgrade) => {
+                    // rebuild the shop so that prices and balance are up to date
+                    if changed && upgrades.buy(*upgrade, &mut stats) {
+                        state.restart().expect("Couldn't restart ShopMenu");
+                    }
+                }
+                MenuButton::Difficulty => {
+                    if changed {
+                        let next = level.difficulty.next();
+                        level.set_difficulty(next);
+                    }
+                }
+                MenuButton::Settings => state
+                    .set(AppState::SettingsMenu)
+                    .expect("Couldn't switch state to SettingsMenu"),
+                MenuButton::ScreenEffects => {
+                    if changed {
+                        settings.screen_effects = !settings.screen_effects;
+                    }
+                }
+                MenuButton::WindowSize => {
+                    if changed {
+                        settings.next_window_size();
+                    }
+                }
+                MenuButton::DisplayMode => {
+                    if changed {
+                        settings.display_mode = settings.display_mode.next();
+                    }
+                }
+                MenuButton::Vsync => {
+                    if changed {
+                        settings.vsync = !settings.vsync;
+                    }
+                }
+                MenuButton::Audio => state
+                    .set(AppState::AudioMenu)
+                    .expect("Couldn't switch state to AudioMenu"),
+                MenuButton::VolumeDown(bus) => {
+                    if changed {
+                        settings.change_volume(*bus, -1);
+                    }
+                }
+                MenuButton::VolumeUp(bus) => {
+                    if changed {
+                        settings.change_volume(*bus, 1);
+                    }
+                }
+                MenuButton::Mute => {
+                    if changed {
+                        settings.muted = !settings.muted;
+                    }
+                }
             };
         }
     }
@@ -89,6 +182,45 @@ pub fn read_input_system(
     }
 }
 
+// Keeps the labels of buttons switching between options up to date
+pub fn option_text_system(
+    level: Res<Level>,
+    settings: Res<Settings>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !level.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    for (button, children) in buttons.iter() {
+        let label = match button {
+            MenuButton::Difficulty => difficulty_label(level.difficulty),
+            MenuButton::ScreenEffects => screen_effects_label(&settings),
+            MenuButton::WindowSize => window_size_label(&settings),
+            MenuButton::DisplayMode => display_mode_label(&settings),
+            MenuButton::Vsync => vsync_label(&settings),
+            MenuButton::Mute => mute_label(&settings),
+            _ => continue,
+        };
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].value = label.clone();
+            }
+        }
+    }
+}
+
+pub fn volume_text_system(settings: Res<Settings>, mut texts: Query<(&VolumeText, &mut Text)>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (VolumeText(bus), mut text) in texts.iter_mut() {
+        text.sections[0].value = volume_label(&settings, *bus);
+    }
+}
+
 pub fn text_update_system(random: Res<Random>, mut query: Query<&mut Text, With<InputText>>) {
     for mut text in query.iter_mut() {
         text.sections[0].value = random.seed.clone();
@@ -1,8 +1,9 @@
 use bevy::prelude::*;
 use rand::{thread_rng, Rng};
 
-use crate::menu::structs::{InputText, MenuButton, MenuColors, MenuData, MenuTextures};
-use crate::{AppState, Level, Random};
+use crate::menu::structs::{InputText, MenuButton, MenuColors, MenuData, MenuTextures, VolumeText};
+use crate::settings::{AudioBus, Settings};
+use crate::{AppState, Difficulty, GameMode, Level, Random, RunStats, Upgrades};
 
 pub fn setup_level_end(
     mut commands: Commands,
@@ -11,6 +12,7 @@ pub fn setup_level_end(
     buttons: Vec<(&'static str, MenuButton)>,
     is_positive: Option<bool>,
     level: usize,
+    info: &str,
 ) {
     let menu_entity = commands
         .spawn_bundle(menu_bundle(&colors))
@@ -34,6 +36,7 @@ pub fn setup_level_end(
             };
 
             parent.spawn_bundle(main_text_bundle(&colors, &textures, &title));
+            parent.spawn_bundle(info_text_bundle(&colors, &textures, info));
 
             let rand: usize = thread_rng().gen_range(0..images.len());
             parent.spawn_bundle(image_bundle(images[rand].clone()));
@@ -52,7 +55,7 @@ pub fn setup_main(
     colors: Res<MenuColors>,
     textures: Res<MenuTextures>,
     text: &'static str,
-    buttons: Vec<(&'static str, MenuButton)>,
+    buttons: Vec<(String, MenuButton)>,
 ) {
     let menu_entity = commands
         .spawn_bundle(menu_bundle(&colors))
@@ -74,7 +77,71 @@ pub fn setup_main(
             );
 
             for (text, but) in buttons {
-                spawn_button(&colors, &textures, parent, text, but);
+                spawn_button(&colors, &textures, parent, &text, but);
+            }
+        })
+        .id();
+
+    insert_menu_data(commands, menu_entity);
+}
+
+pub fn setup_list(
+    mut commands: Commands,
+    colors: Res<MenuColors>,
+    textures: Res<MenuTextures>,
+    title: &str,
+    info: &str,
+    buttons: Vec<(String, MenuButton)>,
+) {
+    let menu_entity = commands
+        .spawn_bundle(menu_bundle(&colors))
+        .with_children(|parent| {
+            parent.spawn_bundle(main_text_bundle(&colors, &textures, title));
+            parent.spawn_bundle(info_text_bundle(&colors, &textures, info));
+
+            for (text, but) in buttons {
+                spawn_button(&colors, &textures, parent, &text, but);
+            }
+        })
+        .id();
+
+    insert_menu_data(commands, menu_entity);
+}
+
+// A list whose first rows turn the volume of a bus down and up around its label
+#[allow(clippy::too_many_arguments)]
+pub fn setup_audio_list(
+    mut commands: Commands,
+    colors: Res<MenuColors>,
+    textures: Res<MenuTextures>,
+    title: &str,
+    info: &str,
+    volumes: Vec<(String, AudioBus)>,
+    buttons: Vec<(String, MenuButton)>,
+) {
+    let menu_entity = commands
+        .spawn_bundle(menu_bundle(&colors))
+        .with_children(|parent| {
+            parent.spawn_bundle(main_text_bundle(&colors, &textures, title));
+            parent.spawn_bundle(info_text_bundle(&colors, &textures, info));
+
+            for (text, bus) in volumes {
+                parent.spawn_bundle(row_bundle()).with_children(|parent| {
+                    spawn_small_button(
+                        &colors,
+                        &textures,
+                        parent,
+                        "-",
+                        MenuButton::VolumeDown(bus),
+                    );
+                    parent
+                        .spawn_bundle(row_text_bundle(&colors, &textures, &text))
+                        .insert(VolumeText(bus));
+                    spawn_small_button(&colors, &textures, parent, "+", MenuButton::VolumeUp(bus));
+                });
+            }
+            for (text, but) in buttons {
+                spawn_button(&colors, &textures, parent, &text, but);
             }
         })
         .id();
@@ -95,14 +162,14 @@ fn spawn_button(
     colors: &Res<MenuColors>,
     textures: &Res<MenuTextures>,
     parent: &mut ChildBuilder,
-    text: &'static str,
+    text: &str,
     but: MenuButton,
 ) {
     parent
         .spawn_bundle(button_bundle(colors))
         .with_children(|parent| {
             match but {
-                MenuButton::NewGame | MenuButton::NextLevel => {
+                MenuButton::NewGame | MenuButton::NextLevel | MenuButton::Shop => {
                     parent.spawn_bundle(button_icon_bundle(textures.play.clone()));
                 }
                 MenuButton::RestartLevel | MenuButton::RestartGame => {
@@ -122,6 +189,21 @@ fn spawn_button(
         .insert(but);
 }
 
+fn spawn_small_button(
+    colors: &Res<MenuColors>,
+    textures: &Res<MenuTextures>,
+    parent: &mut ChildBuilder,
+    text: &str,
+    but: MenuButton,
+) {
+    parent
+        .spawn_bundle(small_button_bundle(colors))
+        .with_children(|parent| {
+            parent.spawn_bundle(button_text_bundle(colors, textures, text));
+        })
+        .insert(but);
+}
+
 fn spawn_input_button(
     colors: &Res<MenuColors>,
     textures: &Res<MenuTextures>,
@@ -164,6 +246,38 @@ fn button_bundle(materials: &Res<MenuColors>) -> ButtonBundle {
     }
 }
 
+fn small_button_bundle(materials: &Res<MenuColors>) -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(65.0), Val::Px(65.0)),
+            margin: Rect {
+                left: Val::Px(10.0),
+                right: Val::Px(10.0),
+                top: Val::Px(20.0),
+                bottom: Val::Px(20.0),
+            },
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: materials.normal_button,
+        ..default()
+    }
+}
+
+// Lays its children out side by side, the menu itself stacks them
+fn row_bundle() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    }
+}
+
 fn button_text_bundle(
     colors: &Res<MenuColors>,
     textures: &Res<MenuTextures>,
@@ -189,6 +303,24 @@ fn main_text_bundle(
     )
 }
 
+// Sized like a button, so that the buttons around it line up with the rest of the list
+fn row_text_bundle(
+    colors: &Res<MenuColors>,
+    textures: &Res<MenuTextures>,
+    label: &str,
+) -> TextBundle {
+    text_bundle(
+        colors,
+        textures,
+        label,
+        40.,
+        Some(Style {
+            size: Size::new(Val::Px(280.0), Val::Auto),
+            ..default()
+        }),
+    )
+}
+
 fn info_text_bundle(
     colors: &Res<MenuColors>,
     textures: &Res<MenuTextures>,
@@ -257,7 +389,7 @@ pub fn cleanup_menu(mut commands: Commands, menu_data: Res<MenuData>) {
 
 pub fn start_game_for_level(
     state: &mut ResMut<State<AppState>>,
-    rng: &mut ResMut<Random>,
+    rng: &mut Random,
     level: &mut ResMut<Level>,
 ) {
     rng.make_generator_for_level(level.level);
@@ -267,15 +399,58 @@ pub fn start_game_for_level(
         .expect("Couldn't switch state to InGame")
 }
 
+pub fn difficulty_label(difficulty: Difficulty) -> String {
+    format!("Difficulty: {}", difficulty.name())
+}
+
+pub fn screen_effects_label(settings: &Settings) -> String {
+    format!("Screen effects: {}", on_off(settings.screen_effects))
+}
+
+pub fn window_size_label(settings: &Settings) -> String {
+    let (width, height) = settings.window_size;
+    format!("Window: {}x{}", width, height)
+}
+
+pub fn display_mode_label(settings: &Settings) -> String {
+    format!("Display: {}", settings.display_mode.name())
+}
+
+pub fn vsync_label(settings: &Settings) -> String {
+    format!("VSync: {}", on_off(settings.vsync))
+}
+
+pub fn volume_label(settings: &Settings, bus: AudioBus) -> String {
+    let percent = (settings.volume(bus) * 100.0).round();
+    format!("{}: {}%", bus.name(), percent)
+}
+
+pub fn mute_label(settings: &Settings) -> String {
+    format!("Mute: {}", on_off(settings.muted))
+}
+
+fn on_off(value: bool) -> &'static str {
+    match value {
+        true => "on",
+        false => "off",
+    }
+}
+
 pub fn start_new_game(
     state: &mut ResMut<State<AppState>>,
-    rng: &mut ResMut<Random>,
+    rng: &mut Random,
     level: &mut ResMut<Level>,
+    stats: &mut ResMut<RunStats>,
+    upgrades: &mut ResMut<Upgrades>,
+    mode: GameMode,
 ) {
     if rng.seed.is_empty() {
         rng.new_random_seed();
     }
 
+    level.mode = mode;
     level.reset_level();
+    stats.reset();
+    upgrades.reset();
     start_game_for_level(state, rng, level);
 }
@@ -0,0 +1,267 @@
+use std::fs;
+
+use bevy::prelude::*;
+use bevy::window::{PresentMode, WindowMode, WindowResized};
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_PATH: &str = "settings.ron";
+// the menus are laid out for a window of this height and scaled to the real one
+const UI_REFERENCE_HEIGHT: f32 = 400.0;
+const UI_SCALE_TOLERANCE: f64 = 0.001;
+// volumes are turned up and down in tenths
+const VOLUME_STEP: f32 = 0.1;
+const WINDOW_SIZES: [(f32, f32); 5] = [
+    (640.0, 400.0),
+    (960.0, 600.0),
+    (1280.0, 720.0),
+    (1280.0, 800.0),
+    (1920.0, 1080.0),
+];
+
+// Added after the default plugins, so that the logger is there to report a broken settings file
+pub struct SettingsPlugin {
+    pub load_error: Option<String>,
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(error) = &self.load_error {
+            warn!("Could not read {}: {}", SETTINGS_PATH, error);
+        }
+        app.add_system(scale_ui_with_window)
+            .add_system(apply_window_settings)
+            .add_system(save_settings);
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub enum DisplayMode {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl DisplayMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisplayMode::Windowed => "Windowed",
+            DisplayMode::Borderless => "Borderless",
+            DisplayMode::Fullscreen => "Fullscreen",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            DisplayMode::Windowed => DisplayMode::Borderless,
+            DisplayMode::Borderless => DisplayMode::Fullscreen,
+            DisplayMode::Fullscreen => DisplayMode::Windowed,
+        }
+    }
+
+    fn window_mode(&self) -> WindowMode {
+        match self {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen,
+            DisplayMode::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+// Groups of sounds the player sets the volume of, the master volume scales all the others
+#[derive(Deserialize, Copy, Clone, PartialEq, Eq, Default)]
+pub enum AudioBus {
+    Master,
+    Music,
+    #[default]
+    Sfx,
+    Ui,
+}
+
+impl AudioBus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioBus::Master => "Master",
+            AudioBus::Music => "Music",
+            AudioBus::Sfx => "Effects",
+            AudioBus::Ui => "Interface",
+        }
+    }
+}
+
+// Player preferences shared by the game and the menus, kept in a file next to the game
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    // camera shake and zoom pulses, some players find them uncomfortable
+    pub screen_effects: bool,
+    pub window_size: (f32, f32),
+    pub display_mode: DisplayMode,
+    // the renderer picks it up only when the window is created, so it needs a restart
+    pub vsync: bool,
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub ui_volume: f32,
+    pub muted: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            screen_effects: true,
+            window_size: WINDOW_SIZES[0],
+            display_mode: DisplayMode::Windowed,
+            vsync: true,
+            master_volume: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            ui_volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl Settings {
+    // Runs before the logger exists, so a file that can't be read is handed to the plugin,
+    // which reports it
+    pub fn load() -> (Self, Option<String>) {
+        match fs::read_to_string(SETTINGS_PATH) {
+            Ok(text) => match ron::de::from_str(&text) {
+                Ok(settings) => (settings, None),
+                Err(error) => (Settings::default(), Some(error.to_string())),
+            },
+            Err(_) => (Settings::default(), None),
+        }
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(SETTINGS_PATH, text)?;
+        Ok(())
+    }
+
+    // The UI scale depends on the backend's scale factor, which isn't known before the window
+    // exists, so it's set by the first pass of apply_window_settings
+    pub fn window_descriptor(&self) -> WindowDescriptor {
+        let (width, height) = self.window_size;
+        WindowDescriptor {
+            title: "Mario MIM!".to_string(),
+            width,
+            height,
+            mode: self.display_mode.window_mode(),
+            present_mode: match self.vsync {
+                true => PresentMode::Fifo,
+                false => PresentMode::Immediate,
+            },
+            ..default()
+        }
+    }
+
+    pub fn next_window_size(&mut self) {
+        let current = WINDOW_SIZES
+            .iter()
+            .position(|&size| size == self.window_size);
+        self.window_size = match current {
+            Some(i) => WINDOW_SIZES[(i + 1) % WINDOW_SIZES.len()],
+            None => WINDOW_SIZES[0],
+        };
+    }
+
+    pub fn volume(&self, bus: AudioBus) -> f32 {
+        match bus {
+            AudioBus::Master => self.master_volume,
+            AudioBus::Music => self.music_volume,
+            AudioBus::Sfx => self.sfx_volume,
+            AudioBus::Ui => self.ui_volume,
+        }
+    }
+
+    // Turns the volume up or down by the given number of steps, it stays between silence
+    // and full volume
+    pub fn change_volume(&mut self, bus: AudioBus, steps: i32) {
+        let volume = match bus {
+            AudioBus::Master => &mut self.master_volume,
+            AudioBus::Music => &mut self.music_volume,
+            AudioBus::Sfx => &mut self.sfx_volume,
+            AudioBus::Ui => &mut self.ui_volume,
+        };
+        let step = (*volume / VOLUME_STEP).round() + steps as f32;
+        *volume = (step * VOLUME_STEP).clamp(0.0, 1.0);
+    }
+
+    // What the sounds of a bus are actually played at
+    pub fn output_volume(&self, bus: AudioBus) -> f32 {
+        match (self.muted, bus) {
+            (true, _) => 0.0,
+            (false, AudioBus::Master) => self.master_volume,
+            (false, bus) => self.master_volume * self.volume(bus),
+        }
+    }
+}
+
+// UI sizes are in logical pixels, so overriding the scale factor makes the window always
+// UI_REFERENCE_HEIGHT logical pixels high and the menus grow with it. Setting the override
+// also asks for a new window size, so it's only done after the window was resized.
+fn scale_ui_with_window(mut windows: ResMut<Windows>, mut resized: EventReader<WindowResized>) {
+    for event in resized.iter() {
+        if let Some(window) = windows.get_mut(event.id) {
+            fit_ui_scale(window);
+        }
+    }
+}
+
+fn fit_ui_scale(window: &mut Window) {
+    let scale = window.physical_height().max(1) as f64 / UI_REFERENCE_HEIGHT as f64;
+    if needs_scale(window, scale) {
+        window.set_scale_factor_override(Some(scale));
+    }
+}
+
+fn needs_scale(window: &Window, scale: f64) -> bool {
+    window
+        .scale_factor_override()
+        .is_none_or(|current| (current - scale).abs() > UI_SCALE_TOLERANCE)
+}
+
+// Also runs once at startup, when the window has its size but not the UI scale yet
+fn apply_window_settings(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = settings.display_mode.window_mode();
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+
+        // the size is asked for in the backend's pixels, not in the scaled UI ones
+        let (width, height) = settings.window_size;
+        let backend_scale = window.backend_scale_factor() as f32;
+        let physical_height = height * backend_scale;
+        let scale = physical_height / UI_REFERENCE_HEIGHT;
+        let physical_size = (width * backend_scale, physical_height);
+        let current_size = (
+            window.physical_width() as f32,
+            window.physical_height() as f32,
+        );
+        if mode != WindowMode::Windowed {
+            // the screen decides the size, only the UI follows it
+            fit_ui_scale(window);
+        } else if current_size != physical_size || needs_scale(window, scale as f64) {
+            window.set_scale_factor_override(Some(scale as f64));
+            window.set_resolution(
+                width * backend_scale / scale,
+                height * backend_scale / scale,
+            );
+        }
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        if let Err(error) = settings.save() {
+            warn!("Could not save the settings: {}", error);
+        }
+    }
+}